@@ -2,6 +2,17 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_games;
 mod m20241029_230517_create_queries;
+mod m20241103_120000_create_rate_limits;
+mod m20241106_090000_create_igdb_responses;
+mod m20241108_100000_create_game_references;
+mod m20241110_150000_add_games_search_vector;
+mod m20241112_093000_add_games_trigram_index;
+mod m20241114_110000_recompute_searchable_names;
+mod m20241116_140000_create_game_aliases;
+mod m20241118_100000_recompute_searchable_names_with_numerals;
+mod m20241120_090000_add_games_popularity;
+mod m20241122_100000_widen_queries_query;
+mod m20241124_100000_recompute_searchable_names_keeping_marks;
 
 pub struct Migrator;
 
@@ -11,6 +22,17 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_games::Migration),
             Box::new(m20241029_230517_create_queries::Migration),
+            Box::new(m20241103_120000_create_rate_limits::Migration),
+            Box::new(m20241106_090000_create_igdb_responses::Migration),
+            Box::new(m20241108_100000_create_game_references::Migration),
+            Box::new(m20241110_150000_add_games_search_vector::Migration),
+            Box::new(m20241112_093000_add_games_trigram_index::Migration),
+            Box::new(m20241114_110000_recompute_searchable_names::Migration),
+            Box::new(m20241116_140000_create_game_aliases::Migration),
+            Box::new(m20241118_100000_recompute_searchable_names_with_numerals::Migration),
+            Box::new(m20241120_090000_add_games_popularity::Migration),
+            Box::new(m20241122_100000_widen_queries_query::Migration),
+            Box::new(m20241124_100000_recompute_searchable_names_keeping_marks::Migration),
         ]
     }
 }
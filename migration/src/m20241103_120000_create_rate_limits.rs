@@ -0,0 +1,46 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+const IGDB_RATE_LIMIT: &str = "igdb";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RateLimits::Table)
+                    .if_not_exists()
+                    .col(string(RateLimits::Name).primary_key())
+                    .col(timestamp_with_time_zone(RateLimits::TheoreticalArrival))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(RateLimits::Table)
+                    .columns([RateLimits::Name, RateLimits::TheoreticalArrival])
+                    .values_panic([IGDB_RATE_LIMIT.into(), Expr::current_timestamp().into()])
+                    .on_conflict(OnConflict::column(RateLimits::Name).do_nothing().to_owned())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RateLimits::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RateLimits {
+    Table,
+    Name,
+    TheoreticalArrival,
+}
@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(IgdbResponses::Table)
+                    .if_not_exists()
+                    .col(string_len(IgdbResponses::QueryHash, 64).primary_key())
+                    .col(string(IgdbResponses::Endpoint))
+                    .col(text(IgdbResponses::Query))
+                    .col(json_binary(IgdbResponses::Response))
+                    .col(timestamp(IgdbResponses::FetchedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IgdbResponses::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IgdbResponses {
+    Table,
+    QueryHash,
+    Endpoint,
+    Query,
+    Response,
+    FetchedAt,
+}
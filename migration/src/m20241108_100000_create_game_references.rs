@@ -0,0 +1,193 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// A kind of named resource games reference many of: its table, the junction table linking it
+/// to games, the junction's foreign key column, and the comma-joined `games` column it replaces
+struct Reference {
+    table: &'static str,
+    junction: &'static str,
+    foreign_key: &'static str,
+    games_column: &'static str,
+}
+
+const REFERENCES: [Reference; 4] = [
+    Reference {
+        table: "platforms",
+        junction: "game_platforms",
+        foreign_key: "platform_id",
+        games_column: "platforms",
+    },
+    Reference {
+        table: "genres",
+        junction: "game_genres",
+        foreign_key: "genre_id",
+        games_column: "genres",
+    },
+    Reference {
+        table: "themes",
+        junction: "game_themes",
+        foreign_key: "theme_id",
+        games_column: "themes",
+    },
+    Reference {
+        table: "game_modes",
+        junction: "game_game_modes",
+        foreign_key: "game_mode_id",
+        games_column: "game_modes",
+    },
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        for reference in &REFERENCES {
+            let table = Alias::new(reference.table);
+            let junction = Alias::new(reference.junction);
+            let foreign_key = Alias::new(reference.foreign_key);
+
+            manager
+                .create_table(
+                    Table::create()
+                        .table(table.clone())
+                        .if_not_exists()
+                        .col(integer(Id).primary_key())
+                        .col(string(Name))
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_table(
+                    Table::create()
+                        .table(junction.clone())
+                        .if_not_exists()
+                        .col(integer(GameId))
+                        .col(integer(foreign_key.clone()))
+                        .primary_key(Index::create().col(GameId).col(foreign_key.clone()))
+                        .foreign_key(
+                            ForeignKey::create()
+                                .from(junction.clone(), GameId)
+                                .to(Games::Table, Games::Id)
+                                .on_delete(ForeignKeyAction::Cascade),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .from(junction.clone(), foreign_key.clone())
+                                .to(table.clone(), Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            // The old columns only hold names, so back-fill with negative placeholder ids. They
+            // are swapped for the real IGDB ids once a game referencing them is fetched again
+            db.execute_unprepared(&format!(
+                r#"
+                INSERT INTO {table} (id, name)
+                SELECT -(ROW_NUMBER() OVER (ORDER BY name))::integer, name
+                FROM (
+                    SELECT DISTINCT trim(names.name) AS name
+                    FROM games
+                    CROSS JOIN LATERAL unnest(string_to_array(games.{column}, ',')) AS names(name)
+                ) AS distinct_names
+                WHERE name <> ''
+                "#,
+                table = reference.table,
+                column = reference.games_column,
+            ))
+            .await?;
+
+            db.execute_unprepared(&format!(
+                r#"
+                INSERT INTO {junction} (game_id, {foreign_key})
+                SELECT DISTINCT games.id, {table}.id
+                FROM games
+                CROSS JOIN LATERAL unnest(string_to_array(games.{column}, ',')) AS names(name)
+                JOIN {table} ON {table}.name = trim(names.name)
+                "#,
+                table = reference.table,
+                junction = reference.junction,
+                foreign_key = reference.foreign_key,
+                column = reference.games_column,
+            ))
+            .await?;
+
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Games::Table)
+                        .drop_column(Alias::new(reference.games_column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        for reference in &REFERENCES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Games::Table)
+                        .add_column(string_null(Alias::new(reference.games_column)))
+                        .to_owned(),
+                )
+                .await?;
+
+            db.execute_unprepared(&format!(
+                r#"
+                UPDATE games
+                SET {column} = (
+                    SELECT string_agg({table}.name, ',' ORDER BY {table}.name)
+                    FROM {junction}
+                    JOIN {table} ON {table}.id = {junction}.{foreign_key}
+                    WHERE {junction}.game_id = games.id
+                )
+                "#,
+                table = reference.table,
+                junction = reference.junction,
+                foreign_key = reference.foreign_key,
+                column = reference.games_column,
+            ))
+            .await?;
+
+            manager
+                .drop_table(
+                    Table::drop()
+                        .table(Alias::new(reference.junction))
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .drop_table(Table::drop().table(Alias::new(reference.table)).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Games {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+struct Id;
+
+#[derive(DeriveIden)]
+struct Name;
+
+#[derive(DeriveIden)]
+struct GameId;
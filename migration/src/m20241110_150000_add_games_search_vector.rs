@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Games::Table)
+                    .add_column(ColumnDef::new(Games::SearchVector).custom(Alias::new("tsvector")))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Alternative names aren't stored, so existing games only get theirs once refreshed
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE games
+                SET search_vector =
+                    setweight(to_tsvector('english', name), 'A')
+                    || setweight(to_tsvector('english', coalesce(summary, '')), 'C')
+                "#,
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-games-search_vector")
+                    .table(Games::Table)
+                    .col(Games::SearchVector)
+                    .full_text()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Games::Table)
+                    .drop_column(Games::SearchVector)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Games {
+    Table,
+    SearchVector,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let games = db
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Games::Id, Games::Name, Games::SearchableName])
+                        .from(Games::Table),
+                ),
+            )
+            .await?;
+
+        for game in games {
+            let id: i32 = game.try_get("", "id")?;
+            let name: String = game.try_get("", "name")?;
+            let searchable_name: String = game.try_get("", "searchable_name")?;
+
+            let recomputed = normalise::searchable_name(&name);
+            if recomputed == searchable_name {
+                continue;
+            }
+
+            db.execute(
+                backend.build(
+                    Query::update()
+                        .table(Games::Table)
+                        .value(Games::SearchableName, recomputed)
+                        .and_where(Expr::col(Games::Id).eq(id)),
+                ),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The previous normalisation can't be recovered, and searchable names are always
+        // recomputed from names anyway
+        Ok(())
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+enum Games {
+    Table,
+    Id,
+    Name,
+    SearchableName,
+}
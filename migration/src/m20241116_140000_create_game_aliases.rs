@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GameAliases::Table)
+                    .if_not_exists()
+                    .col(pk_auto(GameAliases::Id))
+                    .col(integer(GameAliases::GameId))
+                    .col(string(GameAliases::Alias))
+                    .col(string(GameAliases::SearchableAlias))
+                    .col(string_len(GameAliases::Source, 16))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(GameAliases::Table, GameAliases::GameId)
+                            .to(Games::Table, Games::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-game_aliases-game_id-source-searchable_alias")
+                    .table(GameAliases::Table)
+                    .col(GameAliases::GameId)
+                    .col(GameAliases::Source)
+                    .col(GameAliases::SearchableAlias)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // `text_pattern_ops` lets the index serve prefix matches (`LIKE 'gta%'`) too
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE INDEX IF NOT EXISTS "idx-game_aliases-searchable_alias"
+                ON game_aliases (searchable_alias text_pattern_ops)
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GameAliases::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameAliases {
+    Table,
+    Id,
+    GameId,
+    Alias,
+    SearchableAlias,
+    Source,
+}
+
+#[derive(DeriveIden)]
+enum Games {
+    Table,
+    Id,
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+/// A column holding the searchable form of another column
+struct Searchable {
+    table: &'static str,
+    source: &'static str,
+    target: &'static str,
+    /// The columns the searchable form is unique together with, if any. Rows whose recomputed
+    /// form duplicates another's are deleted
+    unique_with: &'static [&'static str],
+}
+
+const SEARCHABLE_COLUMNS: [Searchable; 2] = [
+    Searchable {
+        table: "games",
+        source: "name",
+        target: "searchable_name",
+        unique_with: &[],
+    },
+    Searchable {
+        table: "game_aliases",
+        source: "alias",
+        target: "searchable_alias",
+        unique_with: &["game_id", "source"],
+    },
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for searchable in &SEARCHABLE_COLUMNS {
+            let table = Alias::new(searchable.table);
+            let target = Alias::new(searchable.target);
+
+            // Rows without a uniqueness constraint are each in a group of their own
+            let unique_group = match searchable.unique_with {
+                [] => "id".to_string(),
+                columns => columns.join(", "),
+            };
+
+            let rows = db
+                .query_all(
+                    backend.build(
+                        Query::select()
+                            .column(Alias::new("id"))
+                            .expr_as(
+                                Expr::col(Alias::new(searchable.source)),
+                                Alias::new("source_value"),
+                            )
+                            .expr_as(Expr::col(target.clone()), Alias::new("current"))
+                            .expr_as(
+                                Expr::cust(format!("concat_ws(',', {unique_group})")),
+                                Alias::new("unique_group"),
+                            )
+                            .from(table.clone()),
+                    ),
+                )
+                .await?;
+
+            let mut rows = rows
+                .into_iter()
+                .map(|row| {
+                    let id: i32 = row.try_get("", "id")?;
+                    let value: String = row.try_get("", "source_value")?;
+                    let current: String = row.try_get("", "current")?;
+                    let group: String = row.try_get("", "unique_group")?;
+                    Ok((id, normalise::searchable_name(&value), current, group))
+                })
+                .collect::<Result<Vec<_>, DbErr>>()?;
+
+            // Rows that keep their searchable form take precedence over ones that would collide
+            // with them
+            rows.sort_by_key(|(_, recomputed, current, _)| recomputed != current);
+
+            let mut seen = HashSet::new();
+            for (id, recomputed, current, group) in rows {
+                let is_unique = seen.insert((group, recomputed.clone()));
+
+                let statement = if !is_unique {
+                    backend.build(
+                        Query::delete()
+                            .from_table(table.clone())
+                            .and_where(Expr::col(Alias::new("id")).eq(id)),
+                    )
+                } else if recomputed != current {
+                    backend.build(
+                        Query::update()
+                            .table(table.clone())
+                            .value(target.clone(), recomputed)
+                            .and_where(Expr::col(Alias::new("id")).eq(id)),
+                    )
+                } else {
+                    continue;
+                };
+
+                db.execute(statement).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The previous normalisation can't be recovered, and searchable names are always
+        // recomputed from names anyway
+        Ok(())
+    }
+}
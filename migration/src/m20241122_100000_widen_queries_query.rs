@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Queries are recorded with the filters searched with, which no longer fit in the query length
+// limit
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Queries::Table)
+                    .modify_column(text(Queries::Query))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Filtered queries don't fit back, and are refetched if searched for again
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM queries WHERE length(query) > 32")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Queries::Table)
+                    .modify_column(string_len(Queries::Query, 32))
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+enum Queries {
+    Table,
+    Query,
+}
@@ -0,0 +1,20 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20241118_100000_recompute_searchable_names_with_numerals as recompute;
+
+/// Recompute searchable names now that a lone `v` or `x` is only read as a Roman numeral where it
+/// ends a title and a lone `i` never is, and only Latin, Greek and Cyrillic diacritics are
+/// stripped
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        recompute::Migration.up(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        recompute::Migration.down(manager).await
+    }
+}
@@ -0,0 +1,281 @@
+//! Normalisation of game names for searching, shared by igdbc and its migrations so that stored
+//! names and incoming queries are normalised identically.
+
+use std::ops::RangeInclusive;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Letters that don't decompose into a base letter and diacritics, and what they are spelt as
+/// in ASCII
+const TRANSLITERATIONS: [(char, &str); 8] = [
+    ('ß', "ss"),
+    ('æ', "ae"),
+    ('ø', "o"),
+    ('ł', "l"),
+    ('œ', "oe"),
+    ('þ', "th"),
+    ('đ', "d"),
+    ('ð', "d"),
+];
+
+/// Characters dropped without splitting a word, so that e.g. "L.A." reads as "la" rather than
+/// the words "l" and "a"
+const JOINERS: [char; 4] = ['\'', '’', 'ʼ', '.'];
+
+/// The largest Roman numeral read as a number. Only `i`, `v` and `x` are used up to here, as
+/// `l`, `c`, `d` and `m` on their own are far more often initials than numbers
+const MAX_ROMAN_NUMERAL: u32 = 39;
+
+/// Punctuation between a title and its subtitle, as in "Civilization V: Gods & Kings". Dashes
+/// only separate a subtitle when followed by a space, as "X-Men" is a single name
+const SUBTITLE_SEPARATORS: [char; 2] = [':', '('];
+const DASHES: [char; 3] = ['-', '–', '—'];
+
+/// Stands in for subtitle separators while folding, as every other kind of whitespace is folded
+/// into spaces
+const TITLE_BREAK: char = '\n';
+
+/// The combining diacritics of Latin, Greek and Cyrillic letters, which are stripped. Combining
+/// marks of other scripts, such as Indic vowel signs and the kana voicing marks, change which
+/// letter is meant, so are kept
+const DIACRITICS: [RangeInclusive<char>; 5] = [
+    '\u{0300}'..='\u{036F}',
+    '\u{0483}'..='\u{0489}',
+    '\u{1AB0}'..='\u{1AFF}',
+    '\u{1DC0}'..='\u{1DFF}',
+    '\u{FE20}'..='\u{FE2F}',
+];
+
+/// Reduce a name to a form that differently written names of the same game share:
+///
+/// 1. Compatibility decomposed (so that e.g. full-width letters become plain ones), stripped of
+///    Latin, Greek and Cyrillic diacritics, lowercased and transliterated
+/// 2. Split into words on anything other than letters, digits and the combining marks that
+///    remain, which treats every subtitle separator (`:`, `-`, `–`, ...) alike, with `&` read as
+///    the word "and"
+/// 3. Numbers written in Roman numerals converted to Arabic ones, and leading zeros dropped. A
+///    lone `v` or `x` is only read as a number where it ends a title after other words, as in
+///    "Final Fantasy X", and a lone `i` never is, as "I Am Setsuna" is not "1 Am Setsuna"
+/// 4. The words joined back together without spaces, and recomposed
+///
+/// ```
+/// assert_eq!(normalise::searchable_name("Pokémon: Let's Go!"), "pokemonletsgo");
+/// assert_eq!(
+///     normalise::searchable_name("Final Fantasy VII"),
+///     normalise::searchable_name("final fantasy 7"),
+/// );
+/// ```
+pub fn searchable_name(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+    let mut chars = name.nfkd().flat_map(char::to_lowercase).peekable();
+
+    while let Some(char) = chars.next() {
+        if is_diacritic(char) || JOINERS.contains(&char) {
+            continue;
+        }
+
+        let spaced_dash =
+            DASHES.contains(&char) && chars.peek().is_some_and(|next| next.is_whitespace());
+
+        if char == '&' {
+            folded.push_str(" and ");
+        } else if SUBTITLE_SEPARATORS.contains(&char) || spaced_dash {
+            folded.push(TITLE_BREAK);
+        } else if !(char.is_alphanumeric() || is_combining_mark(char)) {
+            folded.push(' ');
+        } else if let Some((_, to)) = TRANSLITERATIONS.iter().find(|(from, _)| *from == char) {
+            folded.push_str(to);
+        } else {
+            folded.push(char);
+        }
+    }
+
+    folded
+        .split(TITLE_BREAK)
+        .flat_map(|title| {
+            let words = title.split_whitespace().collect::<Vec<_>>();
+            let last = words.len().saturating_sub(1);
+            words
+                .into_iter()
+                .enumerate()
+                .map(move |(index, word)| canonical_word(word, index > 0 && index == last))
+        })
+        .collect::<String>()
+        .nfc()
+        .collect()
+}
+
+fn is_diacritic(char: char) -> bool {
+    DIACRITICS.iter().any(|range| range.contains(&char))
+}
+
+/// `word` in canonical form, given whether it ends a title that has other words before it
+fn canonical_word(word: &str, ends_title: bool) -> String {
+    if word.chars().all(|char| char.is_ascii_digit()) {
+        let trimmed = word.trim_start_matches('0');
+        return if trimmed.is_empty() { "0" } else { trimmed }.to_string();
+    }
+
+    let numeral = match word {
+        "v" if ends_title => Some(5),
+        "x" if ends_title => Some(10),
+        _ => roman_numeral(word),
+    };
+    match numeral {
+        Some(value) => value.to_string(),
+        None => word.to_string(),
+    }
+}
+
+/// The value of a lowercase Roman numeral, if `word` is one of two or more letters written in
+/// canonical form (so not e.g. "iiii" or "vx") no greater than [`MAX_ROMAN_NUMERAL`]
+fn roman_numeral(word: &str) -> Option<u32> {
+    if word.len() < 2 {
+        return None;
+    }
+
+    let digit = |char| match char {
+        'i' => Some(1),
+        'v' => Some(5),
+        'x' => Some(10),
+        _ => None,
+    };
+
+    let digits = word.chars().map(digit).collect::<Option<Vec<u32>>>()?;
+
+    let value = digits
+        .iter()
+        .enumerate()
+        .map(|(index, &digit)| match digits.get(index + 1) {
+            Some(&next) if next > digit => -(digit as i64),
+            _ => digit as i64,
+        })
+        .sum::<i64>();
+
+    let value = u32::try_from(value).ok()?;
+    (1..=MAX_ROMAN_NUMERAL)
+        .contains(&value)
+        .then_some(value)
+        .filter(|&value| to_roman_numeral(value) == word)
+}
+
+fn to_roman_numeral(mut value: u32) -> String {
+    const NUMERALS: [(u32, &str); 5] = [(10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")];
+
+    let mut numeral = String::new();
+    for (step, symbol) in NUMERALS {
+        while value >= step {
+            numeral.push_str(symbol);
+            value -= step;
+        }
+    }
+    numeral
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalises_names() {
+        let cases = [
+            // Case and diacritics
+            ("Pokémon", "pokemon"),
+            ("Ōkami HD", "okamihd"),
+            ("ＦＩＮＡＬ ＦＡＮＴＡＳＹ", "finalfantasy"),
+            // Punctuation and whitespace
+            ("Half-Life 2: Episode One", "halflife2episodeone"),
+            ("«NieR：Automata»", "nierautomata"),
+            ("Baldur’s Gate\u{3000}3", "baldursgate3"),
+            ("L.A. Noire", "lanoire"),
+            // Transliteration
+            ("Straße", "strasse"),
+            ("Ænima Øresund Łódź", "aenimaoresundlodz"),
+            ("Þrymskviða", "thrymskvida"),
+            // Greek and Cyrillic diacritics
+            ("Ἀθῆναι", "αθηναι"),
+            ("Йошкар-Ола", "иошкарола"),
+            // Marks that are part of the letter
+            ("ドラゴンクエスト", "ドラゴンクエスト"),
+            ("ﾄﾞﾗｺﾞﾝｸｴｽﾄ", "ドラゴンクエスト"),
+            ("हिन्दी", "हिन्दी"),
+            // Numbers
+            ("Final Fantasy VII", "finalfantasy7"),
+            ("Final Fantasy Ⅶ", "finalfantasy7"),
+            ("Civilization IV", "civilization4"),
+            ("Final Fantasy XIV", "finalfantasy14"),
+            ("Rocky II", "rocky2"),
+            ("Rocky XXXIX", "rocky39"),
+            ("GoldenEye 007", "goldeneye7"),
+            ("Fallout 76", "fallout76"),
+            ("Zero 0", "zero0"),
+            // Not Roman numerals
+            ("Vivid", "vivid"),
+            ("XL Party", "xlparty"),
+            ("iiii", "iiii"),
+            ("VX Ace", "vxace"),
+            ("Deus Ex", "deusex"),
+            ("I Am Setsuna", "iamsetsuna"),
+            ("Halo I", "haloi"),
+            ("X-Men", "xmen"),
+            ("Star Wars: X-Wing", "starwarsxwing"),
+            ("V Rising", "vrising"),
+            ("X: Beyond the Frontier", "xbeyondthefrontier"),
+            (
+                "Ace Combat X Skies of Deception",
+                "acecombatxskiesofdeception",
+            ),
+            // Lone numerals ending a title
+            ("Final Fantasy X", "finalfantasy10"),
+            ("Grand Theft Auto V", "grandtheftauto5"),
+            ("Civilization V: Gods & Kings", "civilization5godsandkings"),
+            (
+                "Ace Combat X - Skies of Deception",
+                "acecombat10skiesofdeception",
+            ),
+            ("Final Fantasy X (HD Remaster)", "finalfantasy10hdremaster"),
+            ("Mega Man X", "megaman10"),
+            // Conjunctions
+            ("Ratchet & Clank", "ratchetandclank"),
+            ("Ratchet&Clank", "ratchetandclank"),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(searchable_name(name), expected, "normalising {name:?}");
+        }
+    }
+
+    #[test]
+    fn equates_differently_written_names() {
+        let cases = [
+            ("final fantasy 7", "Final Fantasy VII"),
+            ("final fantasy vii", "FINAL FANTASY 7"),
+            ("ff 07", "FF VII"),
+            ("civ vi", "Civ 6"),
+            ("gta 5", "GTA V"),
+            ("grand theft auto 5", "Grand Theft Auto V"),
+            ("civilization 5", "Civilization V"),
+            ("final fantasy 10", "Final Fantasy X"),
+            (
+                "civilization 5 - gods and kings",
+                "Civilization V: Gods & Kings",
+            ),
+            ("ratchet and clank", "Ratchet & Clank"),
+            ("halo: reach", "Halo - Reach"),
+            ("halo — reach", "Halo – Reach"),
+            ("the witcher 3 wild hunt", "The Witcher III: Wild Hunt"),
+            ("pokemon", "Pokémon"),
+            ("la noire", "L.A. Noire"),
+            ("ガ", "ｶﾞ"),
+        ];
+
+        for (query, name) in cases {
+            assert_eq!(
+                searchable_name(query),
+                searchable_name(name),
+                "normalising {query:?} and {name:?}"
+            );
+        }
+    }
+}
@@ -1,28 +1,383 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::igdb::apicalypse::Sort;
+
+const DEFAULT_TWITCH_OAUTH2_URL: &str = "https://id.twitch.tv/oauth2/token";
+const DEFAULT_IGDB_BASE_URL: &str = "https://api.igdb.com/v4";
+const DEFAULT_SEARCH_RESULT_CAP: u64 = 500;
+const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 4;
+const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 250;
+const DEFAULT_RETRY_MAX_DELAY_MS: u64 = 10_000;
+// IGDB allows 4 requests per second and up to 8 open requests at once
+const DEFAULT_RATE_LIMIT_REQUESTS_PER_SECOND: f64 = 4.0;
+const DEFAULT_RATE_LIMIT_BURST: u32 = 4;
+const DEFAULT_RATE_LIMIT_MAX_CONCURRENT: usize = 8;
+// pg_trgm's own default for the `%` operator
+const DEFAULT_FUZZY_SEARCH_THRESHOLD: f64 = 0.3;
+const DEFAULT_MAX_PAGE_SIZE: usize = 50;
+// As long as the HMAC-SHA256 output, so that the key is no easier to guess than a signature
+const MIN_CURSOR_SECRET_LENGTH: usize = 32;
+const DEFAULT_RANKING_EXACT_MATCH: f64 = 8.0;
+const DEFAULT_RANKING_PREFIX_MATCH: f64 = 4.0;
+const DEFAULT_RANKING_TEXT_RANK: f64 = 2.0;
+const DEFAULT_RANKING_SIMILARITY: f64 = 2.0;
+const DEFAULT_RANKING_RATING: f64 = 1.0;
+const DEFAULT_RANKING_RATING_COUNT: f64 = 0.5;
+const DEFAULT_RANKING_POPULARITY: f64 = 0.25;
+const DEFAULT_PASSTHROUGH_CACHE_TTL_SECS: u64 = 60 * 60 * 24;
+const DEFAULT_PASSTHROUGH_ENDPOINTS: [&str; 14] = [
+    "age_ratings",
+    "collections",
+    "companies",
+    "covers",
+    "external_games",
+    "franchises",
+    "game_modes",
+    "games",
+    "genres",
+    "involved_companies",
+    "platforms",
+    "release_dates",
+    "themes",
+    "websites",
+];
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub address: String,
     pub twitch: Twitch,
+    pub igdb: Igdb,
+    pub search: Search,
+    pub passthrough: Passthrough,
+    pub admin: Admin,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Twitch {
     pub client_id: String,
     pub client_secret: String,
+    /// The Twitch OAuth2 token endpoint used to authenticate against IGDB
+    pub oauth_url: Url,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Igdb {
+    /// The base URL that every IGDB endpoint (e.g. `games`) is resolved against
+    pub base_url: Url,
+    /// The maximum number of games fetched from IGDB when refreshing the cache for a search. A
+    /// warning is logged when a search has more results than this
+    pub search_result_cap: usize,
+    /// How IGDB ranks results when filling the cache, e.g. `total_rating_count` `desc` for the
+    /// most rated games first. Unset to use IGDB's search relevance. IGDB can't sort searches, so
+    /// sorted searches only match games whose name contains the query, not alternative names
+    pub search_sort: Option<Sort>,
+    pub retry: Retry,
+    pub rate_limit: RateLimit,
+}
+
+/// How requests that IGDB rate limits (429) or fails (5xx) are retried
+#[derive(Serialize, Deserialize)]
+pub struct Retry {
+    /// The total number of attempts made for a request, including the first
+    pub max_attempts: u32,
+    /// The backoff before the first retry, doubled (with jitter) for every retry after it
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+/// The request budget shared by every request this instance makes to IGDB
+#[derive(Serialize, Deserialize)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    /// How many requests may be made back-to-back after a quiet period
+    pub burst: u32,
+    /// How many requests may be in flight at once
+    pub max_concurrent: usize,
+    pub coordination: RateLimitCoordination,
+}
+
+impl RateLimit {
+    fn validate(&self) -> Result<(), config::ConfigError> {
+        if !(self.requests_per_second.is_finite() && self.requests_per_second > 0.0) {
+            return Err(invalid(
+                "igdb.rate_limit.requests_per_second",
+                "must be a positive number",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Who the request budget is shared with
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitCoordination {
+    /// Each instance has its own budget
+    Local,
+    /// Every instance using the same database shares one budget. The in-flight limit remains
+    /// per-instance
+    Postgres,
+}
+
+/// How games are searched for in the database
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Search {
+    pub fuzzy: FuzzySearch,
+    pub ranking: Ranking,
+    /// The most games a client may ask for in one page of search results
+    pub max_page_size: usize,
+    /// The key the cursors of paged results are signed with, which every instance serving the
+    /// same clients must share. A random one is used if unset, so cursors stop working when
+    /// igdbc restarts
+    pub cursor_secret: Option<String>,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            fuzzy: FuzzySearch::default(),
+            ranking: Ranking::default(),
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            cursor_secret: None,
+        }
+    }
+}
+
+impl Search {
+    fn validate(&self) -> Result<(), config::ConfigError> {
+        // pg_trgm rejects anything else, which would otherwise fail every fuzzy search
+        if !(0.0..=1.0).contains(&self.fuzzy.threshold) {
+            return Err(invalid(
+                "search.fuzzy.threshold",
+                "must be a number from 0 to 1",
+            ));
+        }
+        if self
+            .cursor_secret
+            .as_ref()
+            .is_some_and(|secret| secret.len() < MIN_CURSOR_SECRET_LENGTH)
+        {
+            return Err(invalid(
+                "search.cursor_secret",
+                &format!("must be at least {MIN_CURSOR_SECRET_LENGTH} bytes long"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Typo-tolerant matching of names by trigram similarity, used when other searches find too few
+/// games
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FuzzySearch {
+    pub enabled: bool,
+    /// How similar, from 0 to 1, a name must be to the query to match. Lower tolerates more typos
+    /// but lets in more unrelated games
+    pub threshold: f64,
+}
+
+/// How much each signal adds to a game's relevance to a search. Matches, the text rank and
+/// similarity score from 0 to 1, as does the aggregated rating once scaled down from 0-100.
+/// Rating counts and popularity are unbounded, so are scored logarithmically
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ranking {
+    /// The query is the game's name or one of its aliases
+    pub exact_match: f64,
+    /// The game's name or one of its aliases starts with the query
+    pub prefix_match: f64,
+    /// How well the query matches the game's names and summary as text
+    pub text_rank: f64,
+    /// How similar the game's name is to the query by trigrams, which ranks names closer to a
+    /// misspelt query higher
+    pub similarity: f64,
+    pub rating: f64,
+    pub rating_count: f64,
+    /// How anticipated the game was, by IGDB's count of users who followed it before release.
+    /// IGDB stops counting once a game is out, so for released games this reflects the hype at
+    /// launch rather than current popularity, which `rating_count` follows more closely
+    pub popularity: f64,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Self {
+            exact_match: DEFAULT_RANKING_EXACT_MATCH,
+            prefix_match: DEFAULT_RANKING_PREFIX_MATCH,
+            text_rank: DEFAULT_RANKING_TEXT_RANK,
+            similarity: DEFAULT_RANKING_SIMILARITY,
+            rating: DEFAULT_RANKING_RATING,
+            rating_count: DEFAULT_RANKING_RATING_COUNT,
+            popularity: DEFAULT_RANKING_POPULARITY,
+        }
+    }
+}
+
+/// The raw Apicalypse endpoint (`POST /igdb/:endpoint`) offered to trusted internal clients
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Passthrough {
+    /// The bearer tokens clients must present. The endpoint rejects every request if empty
+    pub api_keys: Vec<String>,
+    /// How long an IGDB response is served from the database before it is fetched again
+    pub cache_ttl_secs: u64,
+    /// The IGDB endpoints clients may query
+    pub endpoints: Vec<String>,
+    /// Restricts the fields queries against an endpoint may reference. Endpoints not listed here
+    /// allow every field
+    #[serde(default)]
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+impl Passthrough {
+    fn validate(&self) -> Result<(), config::ConfigError> {
+        if crate::routes::igdb::cache_ttl(self.cache_ttl_secs).is_none() {
+            return Err(invalid("passthrough.cache_ttl_secs", "is too long"));
+        }
+        Ok(())
+    }
+}
+
+/// The endpoints for managing igdbc's data, such as curated aliases
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Admin {
+    /// The bearer tokens administrators must present. The endpoints reject every request if empty
+    pub api_keys: Vec<String>,
+}
+
+/// An error for a setting that parses but can't be used
+fn invalid(key: &str, reason: &str) -> config::ConfigError {
+    config::ConfigError::Message(format!("{key} {reason}"))
 }
 
 pub fn get_config() -> Result<Config, config::ConfigError> {
-    config::Config::builder()
+    let config: Config = config::Config::builder()
+        .set_default("twitch.oauth_url", DEFAULT_TWITCH_OAUTH2_URL)?
+        .set_default("igdb.base_url", DEFAULT_IGDB_BASE_URL)?
+        .set_default("igdb.search_result_cap", DEFAULT_SEARCH_RESULT_CAP)?
+        .set_default("igdb.retry.max_attempts", DEFAULT_RETRY_MAX_ATTEMPTS)?
+        .set_default("igdb.retry.base_delay_ms", DEFAULT_RETRY_BASE_DELAY_MS)?
+        .set_default("igdb.retry.max_delay_ms", DEFAULT_RETRY_MAX_DELAY_MS)?
+        .set_default(
+            "igdb.rate_limit.requests_per_second",
+            DEFAULT_RATE_LIMIT_REQUESTS_PER_SECOND,
+        )?
+        .set_default("igdb.rate_limit.burst", DEFAULT_RATE_LIMIT_BURST)?
+        .set_default(
+            "igdb.rate_limit.max_concurrent",
+            DEFAULT_RATE_LIMIT_MAX_CONCURRENT as u64,
+        )?
+        .set_default("igdb.rate_limit.coordination", "local")?
+        .set_default("search.fuzzy.enabled", false)?
+        .set_default("search.fuzzy.threshold", DEFAULT_FUZZY_SEARCH_THRESHOLD)?
+        .set_default("search.max_page_size", DEFAULT_MAX_PAGE_SIZE as u64)?
+        .set_default("search.ranking.exact_match", DEFAULT_RANKING_EXACT_MATCH)?
+        .set_default("search.ranking.prefix_match", DEFAULT_RANKING_PREFIX_MATCH)?
+        .set_default("search.ranking.text_rank", DEFAULT_RANKING_TEXT_RANK)?
+        .set_default("search.ranking.similarity", DEFAULT_RANKING_SIMILARITY)?
+        .set_default("search.ranking.rating", DEFAULT_RANKING_RATING)?
+        .set_default("search.ranking.rating_count", DEFAULT_RANKING_RATING_COUNT)?
+        .set_default("search.ranking.popularity", DEFAULT_RANKING_POPULARITY)?
+        .set_default("passthrough.api_keys", Vec::<String>::new())?
+        .set_default(
+            "passthrough.cache_ttl_secs",
+            DEFAULT_PASSTHROUGH_CACHE_TTL_SECS,
+        )?
+        .set_default(
+            "passthrough.endpoints",
+            DEFAULT_PASSTHROUGH_ENDPOINTS.to_vec(),
+        )?
+        .set_default("admin.api_keys", Vec::<String>::new())?
         .add_source(config::File::with_name("Config.toml").required(false))
         .add_source(
             config::Environment::with_prefix("IGDBC")
                 .try_parsing(true)
                 .separator("__")
                 .list_separator(",")
-                .with_list_parse_key("allowed_origins"),
+                .with_list_parse_key("allowed_origins")
+                .with_list_parse_key("passthrough.api_keys")
+                .with_list_parse_key("passthrough.endpoints")
+                .with_list_parse_key("admin.api_keys"),
         )
         .build()?
-        .try_deserialize()
+        .try_deserialize()?;
+
+    config.igdb.rate_limit.validate()?;
+    config.search.validate()?;
+    config.passthrough.validate()?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limit(requests_per_second: f64) -> RateLimit {
+        RateLimit {
+            requests_per_second,
+            burst: DEFAULT_RATE_LIMIT_BURST,
+            max_concurrent: DEFAULT_RATE_LIMIT_MAX_CONCURRENT,
+            coordination: RateLimitCoordination::Local,
+        }
+    }
+
+    #[test]
+    fn rejects_rate_limits_without_a_positive_rate() {
+        assert!(rate_limit(DEFAULT_RATE_LIMIT_REQUESTS_PER_SECOND)
+            .validate()
+            .is_ok());
+        for requests_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(rate_limit(requests_per_second).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_short_cursor_secrets() {
+        let search = |cursor_secret: Option<&str>| Search {
+            cursor_secret: cursor_secret.map(str::to_string),
+            ..Search::default()
+        };
+
+        assert!(search(None).validate().is_ok());
+        assert!(search(Some(&"k".repeat(MIN_CURSOR_SECRET_LENGTH)))
+            .validate()
+            .is_ok());
+        assert!(search(Some("")).validate().is_err());
+        assert!(search(Some("hunter2")).validate().is_err());
+    }
+
+    #[test]
+    fn rejects_fuzzy_search_thresholds_outside_0_to_1() {
+        let search = |threshold| Search {
+            fuzzy: FuzzySearch {
+                enabled: true,
+                threshold,
+            },
+            ..Search::default()
+        };
+
+        for threshold in [0.0, DEFAULT_FUZZY_SEARCH_THRESHOLD, 1.0] {
+            assert!(search(threshold).validate().is_ok());
+        }
+        for threshold in [-0.1, 1.1, f64::NAN, f64::INFINITY] {
+            assert!(search(threshold).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_passthrough_cache_ttls_too_long_to_represent() {
+        let passthrough = |cache_ttl_secs| Passthrough {
+            cache_ttl_secs,
+            ..Passthrough::default()
+        };
+
+        assert!(passthrough(DEFAULT_PASSTHROUGH_CACHE_TTL_SECS)
+            .validate()
+            .is_ok());
+        assert!(passthrough(u64::MAX).validate().is_err());
+        assert!(passthrough(i64::MAX as u64).validate().is_err());
+    }
 }
@@ -1,7 +1,14 @@
 use migration::MigratorTrait;
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::{Database, DatabaseConnection, DbErr};
 
 pub async fn init_database(db: &DatabaseConnection) -> Result<(), DbErr> {
     migration::Migrator::up(db, None).await?;
     Ok(())
 }
+
+/// Connect to the database and bring its schema up to date
+pub async fn connect(db_url: &str) -> Result<DatabaseConnection, DbErr> {
+    let db = Database::connect(db_url).await?;
+    init_database(&db).await?;
+    Ok(db)
+}
@@ -1,8 +1,16 @@
+use std::sync::Arc;
+
 use axum::response::{IntoResponse, Response};
+use axum::Json;
 use reqwest::StatusCode;
+use serde_json::json;
 use thiserror::Error;
 use tracing::{error, trace};
 
+use crate::igdb::apicalypse::parser::ParseError;
+use crate::igdb::apicalypse::policy::PolicyViolation;
+use crate::igdb::IgdbError;
+use crate::routes::admin::AliasError;
 use crate::routes::games::GameFetchError;
 
 #[allow(clippy::large_enum_variant)]
@@ -22,14 +30,30 @@ pub enum IgdbcError {
     #[error("Axum Error {0:?}")]
     Hyper(#[from] hyper::Error),
 
+    #[error("IGDB Error {0}")]
+    Igdb(#[from] IgdbError),
+
+    #[error("Invalid Apicalypse query: {0}")]
+    Apicalypse(#[from] ParseError),
+
+    #[error("{0}")]
+    Policy(#[from] PolicyViolation),
+
     #[error("Could not find game with id {0}")]
     Status(StatusCode),
 
     #[error("Error fetching games: {0}")]
     GameFetch(GameFetchError),
 
+    #[error("Invalid alias: {0}")]
+    Alias(AliasError),
+
     #[error("{0}")]
     Custom(String),
+
+    /// An error from work shared between several requests, e.g. a deduplicated IGDB search
+    #[error("{0}")]
+    Shared(Arc<IgdbcError>),
 }
 
 impl From<StatusCode> for IgdbcError {
@@ -44,6 +68,31 @@ impl From<GameFetchError> for IgdbcError {
     }
 }
 
+impl From<AliasError> for IgdbcError {
+    fn from(value: AliasError) -> Self {
+        Self::Alias(value)
+    }
+}
+
+impl IgdbcError {
+    /// The status code for errors that are not the client's fault
+    fn server_status(&self) -> StatusCode {
+        match self {
+            Self::Igdb(IgdbError::Offline) => StatusCode::SERVICE_UNAVAILABLE,
+            // IGDB is still rate limiting us or failing after all retries were used up
+            Self::Igdb(IgdbError::Reqwest(error))
+                if error.status().is_some_and(|status| {
+                    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                }) =>
+            {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Self::Shared(error) => error.server_status(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 impl IntoResponse for IgdbcError {
     fn into_response(self) -> Response {
         trace!("Route returned error: {self}");
@@ -51,9 +100,18 @@ impl IntoResponse for IgdbcError {
         match self {
             Self::Status(code) => code.into_response(),
             Self::GameFetch(error) => error.into_response(),
+            Self::Alias(error) => error.into_response(),
+            Self::Apicalypse(_) => {
+                let message = Json(json!({ "message": self.to_string() }));
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }
+            Self::Policy(_) => {
+                let message = Json(json!({ "message": self.to_string() }));
+                (StatusCode::FORBIDDEN, message).into_response()
+            }
             _ => {
                 error!("Route returned unhandled error: {self}");
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                self.server_status().into_response()
             }
         }
     }
@@ -0,0 +1,635 @@
+use std::fmt::{self, Display};
+use std::ops::{BitAnd, BitOr};
+
+use itertools::Itertools;
+
+use super::literal::Quoted;
+
+/// A typed Apicalypse `where` expression. Build one starting from [`field`] and combine
+/// expressions with `&` and `|`:
+///
+/// ```
+/// use igdbc::igdb::apicalypse::filter::field;
+///
+/// let filter = field("category").eq(0) & field("parent_game").is_null();
+/// assert_eq!(filter.to_string(), "category = 0 & parent_game = null");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Compare {
+        field: String,
+        op: Comparison,
+        value: Value,
+    },
+    Array {
+        field: String,
+        negated: bool,
+        kind: ArrayMatch,
+        values: Vec<Value>,
+    },
+    Text {
+        field: String,
+        negated: bool,
+        case_insensitive: bool,
+        kind: TextMatch,
+        value: String,
+    },
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// How an array field is matched against a list of values
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArrayMatch {
+    /// The field contains all of the values, `[..]`
+    All,
+    /// The field contains at least one of the values, `(..)`
+    Any,
+    /// The field contains exactly the values and nothing else, `{..}`
+    Exactly,
+}
+
+/// Where in a string field a value must appear
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextMatch {
+    /// The whole field. Only useful case-insensitively, as otherwise it is [`Field::eq`]
+    Exact,
+    Prefix,
+    Postfix,
+    Infix,
+}
+
+/// A literal in a [`Filter`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+/// Start building a [`Filter`] on a (possibly nested, e.g. `platforms.name`) field
+pub fn field(name: impl ToString) -> Field {
+    Field(name.to_string())
+}
+
+pub struct Field(String);
+
+impl Field {
+    fn compare(self, op: Comparison, value: impl Into<Value>) -> Filter {
+        Filter::Compare {
+            field: self.0,
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::Eq, value)
+    }
+
+    pub fn not_eq(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::NotEq, value)
+    }
+
+    pub fn gt(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::Gt, value)
+    }
+
+    pub fn ge(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::Ge, value)
+    }
+
+    pub fn lt(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::Lt, value)
+    }
+
+    pub fn le(self, value: impl Into<Value>) -> Filter {
+        self.compare(Comparison::Le, value)
+    }
+
+    pub fn is_null(self) -> Filter {
+        self.compare(Comparison::Eq, Value::Null)
+    }
+
+    pub fn is_not_null(self) -> Filter {
+        self.compare(Comparison::NotEq, Value::Null)
+    }
+
+    fn array<V: Into<Value>>(
+        self,
+        negated: bool,
+        kind: ArrayMatch,
+        values: impl IntoIterator<Item = V>,
+    ) -> Filter {
+        Filter::Array {
+            field: self.0,
+            negated,
+            kind,
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The field contains every one of the values
+    pub fn contains_all<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.array(false, ArrayMatch::All, values)
+    }
+
+    /// The field contains at least one of the values. Also usable on non-array fields to match
+    /// any one of several values
+    pub fn contains_any<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.array(false, ArrayMatch::Any, values)
+    }
+
+    /// The field contains exactly the values, in any order
+    pub fn contains_exactly<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.array(false, ArrayMatch::Exactly, values)
+    }
+
+    /// The field does not contain all of the values (but may contain some)
+    pub fn not_contains_all<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.array(true, ArrayMatch::All, values)
+    }
+
+    /// The field contains none of the values
+    pub fn not_contains_any<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.array(true, ArrayMatch::Any, values)
+    }
+
+    /// The field contains anything other than exactly the values
+    pub fn not_contains_exactly<V: Into<Value>>(
+        self,
+        values: impl IntoIterator<Item = V>,
+    ) -> Filter {
+        self.array(true, ArrayMatch::Exactly, values)
+    }
+
+    /// Match part of a string field. Prefer the shorthands such as [`Field::starts_with`]
+    pub fn text(
+        self,
+        kind: TextMatch,
+        value: impl ToString,
+        negated: bool,
+        case_insensitive: bool,
+    ) -> Filter {
+        Filter::Text {
+            field: self.0,
+            negated,
+            case_insensitive,
+            kind,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn starts_with(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Prefix, value, false, false)
+    }
+
+    pub fn ends_with(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Postfix, value, false, false)
+    }
+
+    pub fn contains_text(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Infix, value, false, false)
+    }
+
+    /// Case-insensitive [`Field::eq`] for strings
+    pub fn eq_ignore_case(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Exact, value, false, true)
+    }
+
+    /// Case-insensitive [`Field::starts_with`]
+    pub fn starts_with_ignore_case(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Prefix, value, false, true)
+    }
+
+    /// Case-insensitive [`Field::ends_with`]
+    pub fn ends_with_ignore_case(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Postfix, value, false, true)
+    }
+
+    /// Case-insensitive [`Field::contains_text`]
+    pub fn contains_text_ignore_case(self, value: impl ToString) -> Filter {
+        self.text(TextMatch::Infix, value, false, true)
+    }
+}
+
+impl Filter {
+    /// Every field this filter references
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            Filter::Compare { field, .. }
+            | Filter::Array { field, .. }
+            | Filter::Text { field, .. } => vec![field.as_str()],
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().flat_map(Filter::fields).collect()
+            }
+        }
+    }
+
+    /// Combine two filters, flattening nested groups of the same operator
+    fn combine(self, other: Filter, and: bool) -> Filter {
+        let mut filters = Vec::new();
+
+        for filter in [self, other] {
+            match filter {
+                Filter::And(inner) if and => filters.extend(inner),
+                Filter::Or(inner) if !and => filters.extend(inner),
+                filter => filters.push(filter),
+            }
+        }
+
+        if and {
+            Filter::And(filters)
+        } else {
+            Filter::Or(filters)
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::And(_) | Filter::Or(_) => write!(f, "({self})"),
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+impl BitAnd for Filter {
+    type Output = Filter;
+
+    fn bitand(self, rhs: Filter) -> Filter {
+        self.combine(rhs, true)
+    }
+}
+
+impl BitOr for Filter {
+    type Output = Filter;
+
+    fn bitor(self, rhs: Filter) -> Filter {
+        self.combine(rhs, false)
+    }
+}
+
+/// Always holds, as every IGDB resource has an id. Apicalypse has no literal for it, so this
+/// stands in for filters that are trivially true, such as an empty `And`
+const ALWAYS: &str = "id != null";
+/// Never holds, standing in for filters that are trivially false, such as an empty `Or`
+const NEVER: &str = "id = null";
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // NaN equals, and is ordered against, nothing
+            Filter::Compare {
+                op,
+                value: Value::Float(float),
+                ..
+            } if float.is_nan() => f.write_str(if *op == Comparison::NotEq {
+                ALWAYS
+            } else {
+                NEVER
+            }),
+            Filter::Compare { field, op, value } => write!(f, "{field} {op} {value}"),
+            Filter::Array {
+                field,
+                negated,
+                kind,
+                values,
+            } => {
+                let is_nan =
+                    |value: &&Value| matches!(value, Value::Float(float) if float.is_nan());
+                // A field can't contain NaN, so it only makes a difference when every value
+                // must be contained
+                let contains_nan = values.iter().any(|value| is_nan(&value));
+                let values = values.iter().filter(|value| !is_nan(value)).collect_vec();
+
+                let holds = match kind {
+                    ArrayMatch::All | ArrayMatch::Exactly if contains_nan => Some(false),
+                    ArrayMatch::All if values.is_empty() => Some(true),
+                    ArrayMatch::Any if values.is_empty() => Some(false),
+                    _ => None,
+                };
+                if let Some(holds) = holds {
+                    return f.write_str(if holds != *negated { ALWAYS } else { NEVER });
+                }
+                if values.is_empty() {
+                    // Containing exactly nothing
+                    let op = if *negated { "!=" } else { "=" };
+                    return write!(f, "{field} {op} null");
+                }
+
+                let (open, close) = match kind {
+                    ArrayMatch::All => ('[', ']'),
+                    ArrayMatch::Any => ('(', ')'),
+                    ArrayMatch::Exactly => ('{', '}'),
+                };
+                let negation = if *negated { "!" } else { "" };
+
+                write!(
+                    f,
+                    "{field} = {negation}{open}{}{close}",
+                    values.iter().join(",")
+                )
+            }
+            Filter::Text {
+                field,
+                negated,
+                case_insensitive,
+                kind,
+                value,
+            } => {
+                let op = match (negated, case_insensitive) {
+                    (false, false) => "=",
+                    (true, false) => "!=",
+                    (false, true) => "~",
+                    (true, true) => "!~",
+                };
+                let value = Value::String(value.clone());
+
+                match kind {
+                    TextMatch::Exact => write!(f, "{field} {op} {value}"),
+                    TextMatch::Prefix => write!(f, "{field} {op} {value}*"),
+                    TextMatch::Postfix => write!(f, "{field} {op} *{value}"),
+                    TextMatch::Infix => write!(f, "{field} {op} *{value}*"),
+                }
+            }
+            Filter::And(filters) if filters.is_empty() => f.write_str(ALWAYS),
+            Filter::Or(filters) if filters.is_empty() => f.write_str(NEVER),
+            Filter::And(filters) | Filter::Or(filters) => {
+                let separator = if matches!(self, Filter::And(_)) {
+                    " & "
+                } else {
+                    " | "
+                };
+
+                for (index, filter) in filters.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(separator)?;
+                    }
+                    filter.fmt_operand(f)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Eq => "=",
+            Comparison::NotEq => "!=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+        })
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Int(value) => write!(f, "{value}"),
+            // Apicalypse has no infinity, but nothing is beyond the largest float either
+            Value::Float(value) if value.is_infinite() => {
+                write!(f, "{}", value.signum() * f64::MAX)
+            }
+            Value::Float(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{}", Quoted(value)),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Int(value.into())
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(field("rating").eq(80).to_string(), "rating = 80");
+        assert_eq!(field("rating").not_eq(80).to_string(), "rating != 80");
+        assert_eq!(field("rating").gt(80).to_string(), "rating > 80");
+        assert_eq!(field("rating").ge(80).to_string(), "rating >= 80");
+        assert_eq!(field("rating").lt(80).to_string(), "rating < 80");
+        assert_eq!(field("rating").le(80).to_string(), "rating <= 80");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(field("a").eq(true).to_string(), "a = true");
+        assert_eq!(field("a").eq(-3i64).to_string(), "a = -3");
+        assert_eq!(field("a").eq(2.5).to_string(), "a = 2.5");
+        assert_eq!(field("a").eq("Halo").to_string(), "a = \"Halo\"");
+    }
+
+    #[test]
+    fn null_checks() {
+        assert_eq!(
+            field("parent_game").is_null().to_string(),
+            "parent_game = null"
+        );
+        assert_eq!(
+            field("parent_game").is_not_null().to_string(),
+            "parent_game != null"
+        );
+    }
+
+    #[test]
+    fn array_containment() {
+        assert_eq!(
+            field("platforms").contains_all([48, 49]).to_string(),
+            "platforms = [48,49]"
+        );
+        assert_eq!(
+            field("platforms").contains_any([48, 49]).to_string(),
+            "platforms = (48,49)"
+        );
+        assert_eq!(
+            field("platforms").contains_exactly([48, 49]).to_string(),
+            "platforms = {48,49}"
+        );
+        assert_eq!(
+            field("platforms").not_contains_all([48, 49]).to_string(),
+            "platforms = ![48,49]"
+        );
+        assert_eq!(
+            field("platforms").not_contains_any([48, 49]).to_string(),
+            "platforms = !(48,49)"
+        );
+        assert_eq!(
+            field("platforms")
+                .not_contains_exactly([48, 49])
+                .to_string(),
+            "platforms = !{48,49}"
+        );
+    }
+
+    #[test]
+    fn text_matching() {
+        assert_eq!(
+            field("name").starts_with("Halo").to_string(),
+            "name = \"Halo\"*"
+        );
+        assert_eq!(
+            field("name").ends_with("Halo").to_string(),
+            "name = *\"Halo\""
+        );
+        assert_eq!(
+            field("name").contains_text("Halo").to_string(),
+            "name = *\"Halo\"*"
+        );
+        assert_eq!(
+            field("name").eq_ignore_case("Halo").to_string(),
+            "name ~ \"Halo\""
+        );
+        assert_eq!(
+            field("name").starts_with_ignore_case("Halo").to_string(),
+            "name ~ \"Halo\"*"
+        );
+        assert_eq!(
+            field("name").ends_with_ignore_case("Halo").to_string(),
+            "name ~ *\"Halo\""
+        );
+        assert_eq!(
+            field("name").contains_text_ignore_case("Halo").to_string(),
+            "name ~ *\"Halo\"*"
+        );
+        assert_eq!(
+            field("name")
+                .text(TextMatch::Prefix, "Halo", true, false)
+                .to_string(),
+            "name != \"Halo\"*"
+        );
+        assert_eq!(
+            field("name")
+                .text(TextMatch::Infix, "Halo", true, true)
+                .to_string(),
+            "name !~ *\"Halo\"*"
+        );
+    }
+
+    #[test]
+    fn groups_flatten_and_parenthesise() {
+        let filter = field("a").eq(1) & field("b").eq(2) & field("c").eq(3);
+        assert_eq!(filter.to_string(), "a = 1 & b = 2 & c = 3");
+
+        let filter = field("a").eq(1) | field("b").eq(2) | field("c").eq(3);
+        assert_eq!(filter.to_string(), "a = 1 | b = 2 | c = 3");
+
+        let filter = (field("a").eq(1) | field("b").eq(2)) & field("c").eq(3);
+        assert_eq!(filter.to_string(), "(a = 1 | b = 2) & c = 3");
+
+        let filter = field("a").eq(1) | (field("b").eq(2) & field("c").eq(3));
+        assert_eq!(filter.to_string(), "a = 1 | (b = 2 & c = 3)");
+    }
+
+    #[test]
+    fn renders_degenerate_filters_as_valid_apicalypse() {
+        assert_eq!(Filter::And(Vec::new()).to_string(), "id != null");
+        assert_eq!(Filter::Or(Vec::new()).to_string(), "id = null");
+        assert_eq!(
+            (Filter::Or(Vec::new()) & field("a").eq(1)).to_string(),
+            "(id = null) & a = 1"
+        );
+
+        let nothing: [i32; 0] = [];
+        assert_eq!(field("a").contains_any(nothing).to_string(), "id = null");
+        assert_eq!(
+            field("a").not_contains_any(nothing).to_string(),
+            "id != null"
+        );
+        assert_eq!(field("a").contains_all(nothing).to_string(), "id != null");
+        assert_eq!(
+            field("a").not_contains_all(nothing).to_string(),
+            "id = null"
+        );
+        assert_eq!(field("a").contains_exactly(nothing).to_string(), "a = null");
+        assert_eq!(
+            field("a").not_contains_exactly(nothing).to_string(),
+            "a != null"
+        );
+    }
+
+    #[test]
+    fn renders_non_finite_floats_as_valid_apicalypse() {
+        assert_eq!(field("a").ge(f64::NAN).to_string(), "id = null");
+        assert_eq!(field("a").not_eq(f64::NAN).to_string(), "id != null");
+        assert_eq!(
+            field("a").contains_any([1.5, f64::NAN]).to_string(),
+            "a = (1.5)"
+        );
+        assert_eq!(
+            field("a").contains_all([1.5, f64::NAN]).to_string(),
+            "id = null"
+        );
+
+        let infinity = field("a").lt(f64::INFINITY).to_string();
+        assert_eq!(infinity, format!("a < {}", f64::MAX));
+        let negative_infinity = field("a").gt(f64::NEG_INFINITY).to_string();
+        assert_eq!(negative_infinity, format!("a > -{}", f64::MAX));
+        assert!(!infinity.contains("inf"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::{self, Display, Write};
+
+/// Displays a string as an Apicalypse string literal: wrapped in double quotes, with backslashes
+/// and double quotes escaped so that the contents can never terminate the literal early
+pub struct Quoted<'a>(pub &'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+
+        for char in self.0.chars() {
+            if matches!(char, '"' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(char)?;
+        }
+
+        f.write_char('"')
+    }
+}
+
+/// Read a string literal from the start of `input`, returning its unescaped contents and the
+/// input following the closing quote. Returns `None` if `input` doesn't start with a complete
+/// literal
+pub fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.char_indices();
+    let mut contents = String::new();
+
+    if chars.next()?.1 != '"' {
+        return None;
+    }
+
+    while let Some((index, char)) = chars.next() {
+        match char {
+            '"' => return Some((contents, &input[index + 1..])),
+            '\\' => contents.push(chars.next()?.1),
+            char => contents.push(char),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::igdb::apicalypse::filter::field;
+    use crate::igdb::apicalypse::ApicalypseQuery;
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(Quoted("Halo").to_string(), r#""Halo""#);
+        assert_eq!(Quoted(r#"a"b"#).to_string(), r#""a\"b""#);
+        assert_eq!(Quoted(r"a\b").to_string(), r#""a\\b""#);
+        assert_eq!(
+            Quoted(r#"\"; fields *; "#).to_string(),
+            r#""\\\"; fields *; ""#
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_literals() {
+        assert_eq!(parse_quoted(r#""abc"#), None);
+        assert_eq!(parse_quoted(r#""abc\""#), None);
+        assert_eq!(parse_quoted("abc"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn literals_round_trip(value in any::<String>()) {
+            let quoted = Quoted(&value).to_string();
+            prop_assert_eq!(parse_quoted(&quoted), Some((value, "")));
+        }
+
+        #[test]
+        fn search_is_a_single_literal(value in any::<String>()) {
+            let query = ApicalypseQuery::builder().search(&value).to_string();
+            let literal = query.strip_prefix("search ").unwrap();
+
+            prop_assert_eq!(parse_quoted(literal), Some((value, ";\n")));
+        }
+
+        #[test]
+        fn where_values_are_a_single_literal(value in any::<String>()) {
+            let filter = field("name").eq(value.as_str()).to_string();
+            let literal = filter.strip_prefix("name = ").unwrap();
+            prop_assert_eq!(parse_quoted(literal), Some((value.clone(), "")));
+
+            let filter = field("name").contains_text(&value).to_string();
+            let literal = filter.strip_prefix("name = *").unwrap();
+            prop_assert_eq!(parse_quoted(literal), Some((value, "*")));
+        }
+    }
+}
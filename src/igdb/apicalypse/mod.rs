@@ -0,0 +1,295 @@
+use std::fmt::{self, Display};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+pub mod filter;
+pub mod literal;
+pub mod parser;
+pub mod policy;
+use filter::Filter;
+use literal::Quoted;
+
+#[derive(Clone, Debug)]
+pub struct ApicalypseQuery {
+    search: SearchOptions,
+    fields: FieldOptions,
+    exclude: ExcludeOptions,
+    limit: LimitOptions,
+    offset: OffsetOptions,
+    where_clause: WhereOptions,
+    sort: SortOptions,
+}
+
+impl Display for ApicalypseQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.search {
+            SearchOptions::Search(search) => writeln!(f, "search {};", Quoted(search))?,
+            SearchOptions::Unset => (),
+        };
+
+        match &self.fields {
+            FieldOptions::All => writeln!(f, "fields *;")?,
+            FieldOptions::Fields(fields) => writeln!(f, "fields {};", fields.join(", "))?,
+            FieldOptions::Unset => (),
+        };
+
+        match &self.exclude {
+            ExcludeOptions::All => writeln!(f, "exclude *;")?,
+            ExcludeOptions::Fields(fields) => writeln!(f, "exclude {};", fields.join(", "))?,
+            ExcludeOptions::Unset => (),
+        };
+
+        match &self.limit {
+            LimitOptions::Limit(limit) => writeln!(f, "limit {};", limit)?,
+            LimitOptions::Unset => (),
+        };
+
+        match &self.offset {
+            OffsetOptions::Offset(offset) => writeln!(f, "offset {};", offset)?,
+            OffsetOptions::Unset => (),
+        };
+
+        match &self.where_clause {
+            WhereOptions::Where(where_clause) => writeln!(f, "where {};", where_clause)?,
+            WhereOptions::Unset => (),
+        };
+
+        match &self.sort {
+            SortOptions::Sort(sort) => writeln!(f, "sort {} {};", sort.field, sort.direction)?,
+            SortOptions::Unset => (),
+        };
+
+        Ok(())
+    }
+}
+
+impl ApicalypseQuery {
+    pub fn builder() -> Self {
+        Self {
+            fields: FieldOptions::Unset,
+            exclude: ExcludeOptions::Unset,
+            limit: LimitOptions::Unset,
+            where_clause: WhereOptions::Unset,
+            offset: OffsetOptions::Unset,
+            search: SearchOptions::Unset,
+            sort: SortOptions::Unset,
+        }
+    }
+
+    pub fn fields(mut self, fields: Vec<impl ToString>) -> Self {
+        self.fields = FieldOptions::Fields(
+            fields
+                .into_iter()
+                .map(|field| field.to_string())
+                .collect_vec(),
+        );
+        self
+    }
+
+    pub fn fields_all(mut self) -> Self {
+        self.fields = FieldOptions::All;
+        self
+    }
+
+    pub fn exclude(mut self, excluded_fields: Vec<impl ToString>) -> Self {
+        self.exclude = ExcludeOptions::Fields(
+            excluded_fields
+                .into_iter()
+                .map(|field| field.to_string())
+                .collect_vec(),
+        );
+        self
+    }
+
+    pub fn exclude_all(mut self) -> Self {
+        self.exclude = ExcludeOptions::All;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = LimitOptions::Limit(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = OffsetOptions::Offset(offset);
+        self
+    }
+
+    pub fn r#where(mut self, filter: Filter) -> Self {
+        self.where_clause = WhereOptions::Where(filter);
+        self
+    }
+
+    pub fn search(mut self, search: impl ToString) -> Self {
+        self.search = SearchOptions::Search(search.to_string());
+        self
+    }
+
+    /// Order results by a field. IGDB does not support sorting together with `search`
+    pub fn sort(mut self, field: impl ToString, direction: SortDirection) -> Self {
+        self.sort = SortOptions::Sort(Sort {
+            field: field.to_string(),
+            direction,
+        });
+        self
+    }
+
+    /// Order results by a field, unless the query already orders them, either by its own sort or
+    /// by relevance to its search
+    pub fn sort_unless_ordered(self, field: impl ToString, direction: SortDirection) -> Self {
+        match (&self.sort, &self.search) {
+            (SortOptions::Unset, SearchOptions::Unset) => self.sort(field, direction),
+            _ => self,
+        }
+    }
+
+    /// Sort and deduplicate the field lists, so that queries selecting the same fields render
+    /// identically
+    pub fn canonicalize(mut self) -> Self {
+        if let FieldOptions::Fields(fields) = &mut self.fields {
+            fields.sort();
+            fields.dedup();
+        }
+        if let ExcludeOptions::Fields(fields) = &mut self.exclude {
+            fields.sort();
+            fields.dedup();
+        }
+        self
+    }
+
+    /// Every field this query references, whether selected, excluded, filtered or sorted on.
+    /// `fields *` and `exclude *` are reported as `*`
+    pub fn referenced_fields(&self) -> Vec<&str> {
+        let mut referenced = Vec::new();
+
+        match &self.fields {
+            FieldOptions::All => referenced.push("*"),
+            FieldOptions::Fields(fields) => referenced.extend(fields.iter().map(String::as_str)),
+            FieldOptions::Unset => (),
+        }
+        match &self.exclude {
+            ExcludeOptions::All => referenced.push("*"),
+            ExcludeOptions::Fields(fields) => referenced.extend(fields.iter().map(String::as_str)),
+            ExcludeOptions::Unset => (),
+        }
+        if let WhereOptions::Where(filter) = &self.where_clause {
+            referenced.extend(filter.fields());
+        }
+        if let SortOptions::Sort(sort) = &self.sort {
+            referenced.push(&sort.field);
+        }
+
+        referenced
+    }
+
+    /// Add a filter that must hold in addition to any existing `where` clause
+    pub fn and_where(mut self, filter: Filter) -> Self {
+        self.where_clause = match self.where_clause {
+            WhereOptions::Where(where_clause) => WhereOptions::Where(where_clause & filter),
+            WhereOptions::Unset => WhereOptions::Where(filter),
+        };
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ExcludeOptions {
+    Unset,
+    All,
+    Fields(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+enum FieldOptions {
+    Unset,
+    All,
+    Fields(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+enum LimitOptions {
+    Unset,
+    Limit(usize),
+}
+
+#[derive(Clone, Debug)]
+enum OffsetOptions {
+    Unset,
+    Offset(usize),
+}
+
+#[derive(Clone, Debug)]
+enum WhereOptions {
+    Unset,
+    Where(Filter),
+}
+
+#[derive(Clone, Debug)]
+enum SearchOptions {
+    Unset,
+    Search(String),
+}
+
+#[derive(Clone, Debug)]
+enum SortOptions {
+    Unset,
+    Sort(Sort),
+}
+
+/// The field to order results by, and in which direction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl Display for SortDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_only_unordered_queries() {
+        let query = ApicalypseQuery::builder().fields(vec!["name"]);
+
+        assert_eq!(
+            query
+                .clone()
+                .sort_unless_ordered("id", SortDirection::Asc)
+                .to_string(),
+            "fields name;\nsort id asc;\n"
+        );
+        assert_eq!(
+            query
+                .clone()
+                .sort("total_rating_count", SortDirection::Desc)
+                .sort_unless_ordered("id", SortDirection::Asc)
+                .to_string(),
+            "fields name;\nsort total_rating_count desc;\n"
+        );
+        assert_eq!(
+            query
+                .search("Halo")
+                .sort_unless_ordered("id", SortDirection::Asc)
+                .to_string(),
+            "search \"Halo\";\nfields name;\n"
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::filter::{field, Filter, TextMatch, Value};
+use super::literal::parse_quoted;
+use super::{ApicalypseQuery, SortDirection};
+
+/// How deeply parentheses may nest in a `where` clause. Parsing recurses once per level, so this
+/// keeps hostile input from overflowing the stack
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Where in the input a [`ParseError`] occurred. Lines and columns start at 1 and columns count
+/// characters, not bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} at {position}")]
+pub struct ParseError {
+    pub position: Position,
+    pub message: String,
+}
+
+impl FromStr for ApicalypseQuery {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+impl ApicalypseQuery {
+    /// Parse Apicalypse text, e.g. a request body sent to IGDB, into a query. Both the full and
+    /// abbreviated (`f`, `w`, ...) clause names are accepted
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Parser {
+            input,
+            offset: 0,
+            depth: 0,
+        }
+        .query()
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+    /// How many parentheses enclose the current offset
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn query(mut self) -> Result<ApicalypseQuery, ParseError> {
+        let mut query = ApicalypseQuery::builder();
+        let mut seen = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().is_empty() {
+                return Ok(query);
+            }
+
+            let clause_start = self.offset;
+            let identifier = self.identifier()?.to_string();
+            let clause = match identifier.as_str() {
+                "f" | "fields" => "fields",
+                "e" | "exclude" => "exclude",
+                "w" | "where" => "where",
+                "l" | "limit" => "limit",
+                "o" | "offset" => "offset",
+                "s" | "sort" => "sort",
+                "search" => "search",
+                other => {
+                    return Err(self.error_at(clause_start, format!("unknown clause `{other}`")))
+                }
+            };
+
+            if seen.contains(&clause) {
+                return Err(self.error_at(clause_start, format!("duplicate `{clause}` clause")));
+            }
+            seen.push(clause);
+
+            query = match clause {
+                "fields" if self.eat('*') => query.fields_all(),
+                "fields" => query.fields(self.field_list()?),
+                "exclude" if self.eat('*') => query.exclude_all(),
+                "exclude" => query.exclude(self.field_list()?),
+                "where" => query.r#where(self.or()?),
+                "limit" => query.limit(self.unsigned()?),
+                "offset" => query.offset(self.unsigned()?),
+                "search" => query.search(self.string()?),
+                "sort" => {
+                    let field = self.field()?;
+                    let direction = self.sort_direction()?;
+                    query.sort(field, direction)
+                }
+                _ => unreachable!(),
+            };
+
+            self.expect(';')?;
+        }
+    }
+
+    fn field_list(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut fields = vec![self.field()?];
+        while self.eat(',') {
+            fields.push(self.field()?);
+        }
+        Ok(fields)
+    }
+
+    /// A dotted field path. The last segment may be `*` to select every field of an expansion
+    fn field(&mut self) -> Result<String, ParseError> {
+        let mut field = self.identifier()?.to_string();
+
+        while self.rest().starts_with('.') {
+            self.offset += 1;
+            if self.rest().starts_with('*') {
+                self.offset += 1;
+                field.push_str(".*");
+                break;
+            }
+            field.push('.');
+            field.push_str(self.identifier_here()?);
+        }
+
+        Ok(field)
+    }
+
+    fn sort_direction(&mut self) -> Result<SortDirection, ParseError> {
+        self.skip_whitespace();
+        if self.rest().starts_with(';') {
+            return Ok(SortDirection::Asc);
+        }
+
+        let start = self.offset;
+        let identifier = self.identifier()?.to_string();
+        match identifier.as_str() {
+            "asc" => Ok(SortDirection::Asc),
+            "desc" => Ok(SortDirection::Desc),
+            other => {
+                Err(self.error_at(start, format!("expected `asc` or `desc`, found `{other}`")))
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.and()?;
+        while self.eat('|') {
+            filter = filter | self.and()?;
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.atom()?;
+        while self.eat('&') {
+            filter = filter & self.atom()?;
+        }
+        Ok(filter)
+    }
+
+    fn atom(&mut self) -> Result<Filter, ParseError> {
+        let start = self.offset;
+        if self.eat('(') {
+            if self.depth == MAX_NESTING_DEPTH {
+                return Err(self.error_at(
+                    start,
+                    format!("parentheses nested more than {MAX_NESTING_DEPTH} deep"),
+                ));
+            }
+
+            // Parentheses are kept in the tree only by nesting, so `(a & b)` on its own is `a & b`
+            self.depth += 1;
+            let filter = self.or()?;
+            self.expect(')')?;
+            self.depth -= 1;
+            return Ok(filter);
+        }
+
+        let name = self.field()?;
+        self.skip_whitespace();
+
+        let operator = ["!=", ">=", "<=", "!~", "=", ">", "<", "~"]
+            .into_iter()
+            .find(|operator| self.rest().starts_with(operator))
+            .ok_or_else(|| self.error("expected a comparison operator"))?;
+        self.offset += operator.len();
+
+        match operator {
+            "=" | "!=" => self.equality(name, operator == "!="),
+            "~" | "!~" => {
+                self.skip_whitespace();
+                let value_start = self.offset;
+                match self.text_match()? {
+                    Some((kind, value)) => {
+                        Ok(field(name).text(kind, value, operator == "!~", true))
+                    }
+                    None => Err(self.error_at(value_start, "expected a string")),
+                }
+            }
+            _ => {
+                let value = self.value()?;
+                Ok(match operator {
+                    ">" => field(name).gt(value),
+                    ">=" => field(name).ge(value),
+                    "<" => field(name).lt(value),
+                    "<=" => field(name).le(value),
+                    _ => unreachable!(),
+                })
+            }
+        }
+    }
+
+    /// The right hand side of `=` or `!=`: a value, a string match or an array match
+    fn equality(&mut self, name: String, negated: bool) -> Result<Filter, ParseError> {
+        self.skip_whitespace();
+
+        let array_negated = self.rest().starts_with('!');
+        let after_bang = &self.rest()[usize::from(array_negated)..];
+        if let Some(open) = after_bang
+            .chars()
+            .next()
+            .filter(|char| "[({".contains(*char))
+        {
+            if negated {
+                return Err(self.error("array matches can only be negated with `= !`"));
+            }
+            self.offset += usize::from(array_negated) + 1;
+            let close = match open {
+                '[' => ']',
+                '(' => ')',
+                _ => '}',
+            };
+
+            // IGDB rejects empty arrays
+            let mut values = vec![self.value()?];
+            while self.eat(',') {
+                values.push(self.value()?);
+            }
+            self.expect(close)?;
+
+            let field = field(name);
+            return Ok(match (open, array_negated) {
+                ('[', false) => field.contains_all(values),
+                ('(', false) => field.contains_any(values),
+                (_, false) => field.contains_exactly(values),
+                ('[', true) => field.not_contains_all(values),
+                ('(', true) => field.not_contains_any(values),
+                (_, true) => field.not_contains_exactly(values),
+            });
+        }
+
+        let value = match self.text_match()? {
+            Some((TextMatch::Exact, value)) => Value::String(value),
+            Some((kind, value)) => return Ok(field(name).text(kind, value, negated, false)),
+            None => self.value()?,
+        };
+
+        Ok(if negated {
+            field(name).not_eq(value)
+        } else {
+            field(name).eq(value)
+        })
+    }
+
+    /// A string, optionally with a leading and/or trailing `*`. Returns `None`, consuming
+    /// nothing, if the input is some other kind of value
+    fn text_match(&mut self) -> Result<Option<(TextMatch, String)>, ParseError> {
+        self.skip_whitespace();
+
+        let leading = self.eat('*');
+        if !leading && !self.rest().starts_with('"') {
+            return Ok(None);
+        }
+
+        let value = self.string()?;
+        let trailing = self.rest().starts_with('*');
+        if trailing {
+            self.offset += 1;
+        }
+
+        let kind = match (leading, trailing) {
+            (true, true) => TextMatch::Infix,
+            (true, false) => TextMatch::Postfix,
+            (false, true) => TextMatch::Prefix,
+            (false, false) => TextMatch::Exact,
+        };
+
+        Ok(Some((kind, value)))
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('"') {
+            return Ok(Value::String(self.string()?));
+        }
+
+        let start = self.offset;
+        let token_length = self
+            .rest()
+            .find(|char: char| !(char.is_ascii_alphanumeric() || "-+._".contains(char)))
+            .unwrap_or(self.rest().len());
+        let token = &self.rest()[..token_length];
+
+        let value = match token {
+            "null" => Value::Null,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            token => match (token.parse::<i64>(), token.parse::<f64>()) {
+                (Ok(int), _) => Value::Int(int),
+                // Rejects `inf`, `NaN` and friends, which IGDB doesn't understand
+                (_, Ok(float)) if float.is_finite() => Value::Float(float),
+                _ if token.is_empty() => return Err(self.error("expected a value")),
+                _ => return Err(self.error_at(start, format!("invalid value `{token}`"))),
+            },
+        };
+
+        self.offset += token_length;
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+
+        if !self.rest().starts_with('"') {
+            return Err(self.error("expected a string"));
+        }
+
+        let (value, rest) =
+            parse_quoted(self.rest()).ok_or_else(|| self.error("unterminated string"))?;
+        self.offset = self.input.len() - rest.len();
+
+        Ok(value)
+    }
+
+    fn unsigned(&mut self) -> Result<usize, ParseError> {
+        self.skip_whitespace();
+
+        let length = self
+            .rest()
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+
+        let value = self.rest()[..length]
+            .parse()
+            .map_err(|_| self.error("expected a non-negative integer"))?;
+        self.offset += length;
+
+        Ok(value)
+    }
+
+    fn identifier(&mut self) -> Result<&str, ParseError> {
+        self.skip_whitespace();
+        self.identifier_here()
+    }
+
+    /// An identifier starting exactly at the current offset
+    fn identifier_here(&mut self) -> Result<&str, ParseError> {
+        let length = self
+            .rest()
+            .find(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+            .unwrap_or(self.rest().len());
+
+        if length == 0 {
+            return Err(self.error("expected an identifier"));
+        }
+
+        let start = self.offset;
+        self.offset += length;
+        Ok(&self.input[start..self.offset])
+    }
+
+    /// Consume `char` if it is the next non-whitespace character
+    fn eat(&mut self, char: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(char) {
+            self.offset += char.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, char: char) -> Result<(), ParseError> {
+        if self.eat(char) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{char}`")))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.offset..]
+    }
+
+    fn error(&self, message: impl ToString) -> ParseError {
+        self.error_at(self.offset, message)
+    }
+
+    fn error_at(&self, offset: usize, message: impl ToString) -> ParseError {
+        let before = &self.input[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        ParseError {
+            position: Position {
+                offset,
+                line,
+                column: before[line_start..].chars().count() + 1,
+            },
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_clause() {
+        let query = ApicalypseQuery::parse(
+            r#"search "Halo"; f name, cover.url, platforms.*; exclude summary;
+            w (rating >= 80.5 | rating = null) & platforms = (6,48) & name ~ "halo"*;
+            l 10; o 20; s rating desc;"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.to_string(),
+            "search \"Halo\";\nfields name, cover.url, platforms.*;\nexclude summary;\nlimit 10;\noffset 20;\nwhere (rating >= 80.5 | rating = null) & platforms = (6,48) & name ~ \"halo\"*;\nsort rating desc;\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_rendered_queries() {
+        let rendered = ApicalypseQuery::builder()
+            .fields(vec!["id", "name"])
+            .r#where(
+                field("platforms").not_contains_exactly([1, 2])
+                    | field("name").ends_with("\"quoted\"")
+                    | field("parent_game").is_not_null(),
+            )
+            .sort("id", SortDirection::Asc)
+            .to_string();
+
+        assert_eq!(
+            ApicalypseQuery::parse(&rendered).unwrap().to_string(),
+            rendered
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = ApicalypseQuery::parse("fields name;\nwhere rating >;").unwrap_err();
+        assert_eq!(error.position.line, 2);
+        assert_eq!(error.position.column, 15);
+
+        let error = ApicalypseQuery::parse("fields name;\nfields id;").unwrap_err();
+        assert_eq!(error.message, "duplicate `fields` clause");
+        assert_eq!(error.position.column, 1);
+
+        let error = ApicalypseQuery::parse("search \"unterminated;").unwrap_err();
+        assert_eq!(error.message, "unterminated string");
+        assert_eq!(error.position.column, 8);
+
+        let error = ApicalypseQuery::parse("fields name").unwrap_err();
+        assert_eq!(error.message, "expected `;`");
+        assert_eq!(error.position.offset, 11);
+    }
+
+    #[test]
+    fn rejects_values_igdb_does_not_understand() {
+        let error = ApicalypseQuery::parse("where platforms = ();").unwrap_err();
+        assert_eq!(error.message, "expected a value");
+
+        let error = ApicalypseQuery::parse("where rating > 1e999;").unwrap_err();
+        assert_eq!(error.message, "invalid value `1e999`");
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested =
+            |depth: usize| format!("where {}a = 1{};", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(ApicalypseQuery::parse(&nested(MAX_NESTING_DEPTH)).is_ok());
+
+        let error = ApicalypseQuery::parse(&nested(MAX_NESTING_DEPTH + 1)).unwrap_err();
+        assert_eq!(error.message, "parentheses nested more than 32 deep");
+        assert_eq!(error.position.offset, 6 + MAX_NESTING_DEPTH);
+
+        // Deep enough to overflow the stack if it were parsed
+        assert!(ApicalypseQuery::parse(&nested(1_000_000)).is_err());
+    }
+}
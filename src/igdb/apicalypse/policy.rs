@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::ApicalypseQuery;
+
+/// A whitelist of IGDB endpoints, and optionally of the fields queries against each may
+/// reference, used to vet queries supplied by clients before they are sent upstream
+#[derive(Debug, Clone, Default)]
+pub struct QueryPolicy {
+    /// Endpoints mapped to their allowed fields, or `None` if any field is allowed
+    endpoints: HashMap<String, Option<Vec<String>>>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    #[error("Endpoint '{0}' is not allowed")]
+    Endpoint(String),
+
+    #[error("Field '{field}' is not allowed on endpoint '{endpoint}'")]
+    Field { endpoint: String, field: String },
+}
+
+impl QueryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow queries against `endpoint` referencing any field
+    pub fn allow_endpoint(mut self, endpoint: impl ToString) -> Self {
+        self.endpoints.insert(endpoint.to_string(), None);
+        self
+    }
+
+    /// Allow queries against `endpoint` that only reference the given fields. A field ending in
+    /// `.*` allows everything under it, e.g. `cover.*` allows `cover.url`, and `*` allows all
+    pub fn allow_fields(mut self, endpoint: impl ToString, fields: Vec<impl ToString>) -> Self {
+        self.endpoints.insert(
+            endpoint.to_string(),
+            Some(fields.into_iter().map(|field| field.to_string()).collect()),
+        );
+        self
+    }
+
+    pub fn check(&self, endpoint: &str, query: &ApicalypseQuery) -> Result<(), PolicyViolation> {
+        let allowed_fields = self
+            .endpoints
+            .get(endpoint)
+            .ok_or_else(|| PolicyViolation::Endpoint(endpoint.to_string()))?;
+
+        let Some(allowed_fields) = allowed_fields else {
+            return Ok(());
+        };
+
+        match query
+            .referenced_fields()
+            .into_iter()
+            .find(|field| !Self::is_allowed(allowed_fields, field))
+        {
+            Some(field) => Err(PolicyViolation::Field {
+                endpoint: endpoint.to_string(),
+                field: field.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn is_allowed(allowed_fields: &[String], field: &str) -> bool {
+        allowed_fields.iter().any(|allowed| {
+            allowed == "*"
+                || allowed == field
+                || allowed
+                    .strip_suffix('*')
+                    .is_some_and(|prefix| prefix.ends_with('.') && field.starts_with(prefix))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> QueryPolicy {
+        QueryPolicy::new()
+            .allow_endpoint("platforms")
+            .allow_fields("games", vec!["name", "cover.*", "rating"])
+    }
+
+    #[test]
+    fn allows_whitelisted_fields() {
+        let query =
+            ApicalypseQuery::parse("fields name, cover.url; where rating > 80; sort rating desc;")
+                .unwrap();
+        assert_eq!(policy().check("games", &query), Ok(()));
+
+        let query = ApicalypseQuery::parse("fields *;").unwrap();
+        assert_eq!(policy().check("platforms", &query), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_endpoints_and_fields() {
+        let query = ApicalypseQuery::parse("fields name;").unwrap();
+        assert_eq!(
+            policy().check("companies", &query),
+            Err(PolicyViolation::Endpoint("companies".to_string()))
+        );
+
+        let query = ApicalypseQuery::parse("fields name; where summary ~ *\"halo\"*;").unwrap();
+        assert_eq!(
+            policy().check("games", &query),
+            Err(PolicyViolation::Field {
+                endpoint: "games".to_string(),
+                field: "summary".to_string()
+            })
+        );
+
+        let query = ApicalypseQuery::parse("fields *;").unwrap();
+        assert!(policy().check("games", &query).is_err());
+    }
+}
@@ -1,108 +1,398 @@
-use chrono::{Duration, NaiveDateTime, TimeDelta, Utc};
-use reqwest::Client;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use reqwest::{Client, Response, StatusCode};
+use sea_orm::DatabaseConnection;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::{Mutex, Notify};
 use tokio::time::sleep;
+use tracing::{info, warn};
+use url::Url;
+
+use super::apicalypse::filter::field;
+use super::multiquery::{MultiQuery, MultiQueryResponse};
+use super::rate_limit::RateLimiter;
+use super::resources::IgdbResource;
+use super::retry::RetryPolicy;
+use super::{
+    apicalypse::{ApicalypseQuery, Sort, SortDirection},
+    models::TwitchAuthResponse,
+    GameSearch, IgdbApi, IgdbError, IgdbGame, IgdbResult, IgdbStatus,
+};
+use crate::configuration::{Igdb, Twitch};
+
+const MULTIQUERY_ENDPOINT: &str = "multiquery";
+
+/// The largest page IGDB will return for a single request
+pub const MAX_PAGE_SIZE: usize = 500;
 
-use super::{apicalypse::ApicalypseQuery, models::TwitchAuthResponse, IgdbGame};
+/// How long before the access token expires to proactively refresh it
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 300;
+const AUTH_RETRY_INITIAL_DELAY_SECS: u64 = 5;
+const AUTH_RETRY_MAX_DELAY_SECS: u64 = 300;
 
-const TWITCH_OAUTH2_ENDPOINT: &str = "https://id.twitch.tv/oauth2/token?client_id={client_id}&client_secret={client_secret}&grant_type=client_credentials";
-const IGDB_GAMES_URL: &str = "https://api.igdb.com/v4/games";
-const REQUEST_DELAY_MS: i64 = 260;
+struct AccessToken {
+    token: String,
+    expiry: NaiveDateTime,
+}
 
 pub struct IgdbClient {
     client: Client,
     client_id: String,
     client_secret: String,
-    access_token: String,
-    token_expiry: NaiveDateTime,
-    next_request: NaiveDateTime,
+    oauth_url: Url,
+    base_url: Url,
+    access_token: Mutex<Option<AccessToken>>,
+    online: AtomicBool,
+    /// Notified when a request fails to refresh the access token, taking the client offline
+    auth_failed: Notify,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    search_result_cap: usize,
+    search_sort: Option<Sort>,
 }
 
-type IgdbResult<T> = Result<T, reqwest::Error>;
-
 impl IgdbClient {
-    pub async fn new(client_id: String, client_secret: String) -> IgdbResult<Self> {
-        let client = Client::new();
-
-        let response = Self::refresh_access_token(&client, &client_id, &client_secret).await?;
-
-        Ok(Self {
-            client,
-            client_id,
-            client_secret,
-            access_token: response.access_token,
-            token_expiry: Utc::now().naive_utc() + Duration::seconds(response.expires_in.into()),
-            next_request: Utc::now().naive_utc(),
-        })
+    /// Create a client without authenticating. The client stays offline until
+    /// [`IgdbClient::authenticate`] succeeds, either directly or via [`IgdbClient::keep_authenticated`]
+    pub fn new(twitch: &Twitch, igdb: &Igdb, db: &DatabaseConnection) -> Self {
+        Self {
+            client: Client::new(),
+            client_id: twitch.client_id.clone(),
+            client_secret: twitch.client_secret.clone(),
+            oauth_url: twitch.oauth_url.clone(),
+            base_url: igdb.base_url.clone(),
+            access_token: Mutex::new(None),
+            online: AtomicBool::new(false),
+            auth_failed: Notify::new(),
+            retry_policy: RetryPolicy::new(&igdb.retry),
+            rate_limiter: RateLimiter::new(&igdb.rate_limit, db),
+            search_result_cap: igdb.search_result_cap,
+            search_sort: igdb.search_sort.clone(),
+        }
     }
 
-    pub async fn search(&mut self, query: String) -> IgdbResult<Vec<IgdbGame>> {
-        let apicalypse_query = ApicalypseQuery::builder()
-            .search(query)
-            .fields(vec![
-                "id",
-                "name",
-                "url",
-                "summary",
-                "aggregated_rating",
-                "cover.url",
-                "artworks.url",
-                "multiplayer_modes.onlinecoop",
-                "first_release_date",
-                "platforms.name",
-            ])
-            // Only main-games (exclude DLCs etc.)
-            .r#where("category = 0")
-            // As above, in case of upstream incorrect metadata
-            .and_where("parent_game = null")
-            // Exclude versions of games
-            .and_where("version_parent = null")
-            .limit(500);
-
-        if self.token_expiry < Utc::now().naive_utc() {
-            let auth_response =
-                Self::refresh_access_token(&self.client, &self.client_id, &self.client_secret)
+    /// Fetch a fresh access token from Twitch, bringing the client online. Returns when the new
+    /// token expires
+    pub async fn authenticate(&self) -> IgdbResult<NaiveDateTime> {
+        let mut access_token = self.access_token.lock().await;
+        let token = self.fetch_access_token().await?;
+        let expiry = token.expiry;
+
+        *access_token = Some(token);
+
+        Ok(expiry)
+    }
+
+    /// Keep the client authenticated forever: retry with backoff while Twitch is unreachable and
+    /// refresh the token shortly before it expires, or as soon as a request fails to refresh it.
+    /// Meant to be spawned as a background task
+    pub async fn keep_authenticated(self: Arc<Self>) {
+        let initial_delay = std::time::Duration::from_secs(AUTH_RETRY_INITIAL_DELAY_SECS);
+        let max_delay = std::time::Duration::from_secs(AUTH_RETRY_MAX_DELAY_SECS);
+        let mut retry_delay = initial_delay;
+
+        loop {
+            match self.authenticate().await {
+                Ok(expiry) => {
+                    info!("Authenticated with Twitch, IGDB is online");
+                    retry_delay = initial_delay;
+
+                    let refresh_in = expiry
+                        - Utc::now().naive_utc()
+                        - Duration::seconds(TOKEN_REFRESH_MARGIN_SECS);
+                    tokio::select! {
+                        _ = sleep(refresh_in.to_std().unwrap_or(initial_delay)) => {}
+                        _ = self.auth_failed.notified() => {
+                            info!("A request could not refresh the access token, reauthenticating");
+                        }
+                    }
+                }
+                Err(error) => {
+                    warn!(
+                        "Could not authenticate with Twitch, serving from cache only. Retrying in {}s: {error}",
+                        retry_delay.as_secs()
+                    );
+                    sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(max_delay);
+                }
+            }
+        }
+    }
+
+    /// Query any IGDB endpoint, deserializing each result as `T`
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: ApicalypseQuery,
+    ) -> IgdbResult<Vec<T>> {
+        self.post(endpoint, query).await
+    }
+
+    /// Query the endpoint that serves `T`
+    pub async fn query_resource<T: IgdbResource>(
+        &self,
+        query: ApicalypseQuery,
+    ) -> IgdbResult<Vec<T>> {
+        self.query(T::ENDPOINT, query).await
+    }
+
+    /// Stream every result of a query, fetching it a page at a time by walking `offset` until
+    /// IGDB runs out of results or `cap` results have been fetched. Each page is a separate
+    /// rate-limited request, made only once the previous page has been consumed. Queries that
+    /// neither sort nor search are sorted by id, so that pages don't shift between requests
+    pub fn paginate<'a, T>(
+        &'a self,
+        endpoint: &'a str,
+        query: ApicalypseQuery,
+        cap: Option<usize>,
+    ) -> BoxStream<'a, IgdbResult<T>>
+    where
+        T: DeserializeOwned + Send + 'a,
+    {
+        struct Page {
+            offset: usize,
+            exhausted: bool,
+        }
+
+        let initial_page = Page {
+            offset: 0,
+            exhausted: false,
+        };
+
+        let query = query.sort_unless_ordered("id", SortDirection::Asc);
+
+        stream::try_unfold(initial_page, move |page| {
+            let query = query.clone();
+
+            async move {
+                let remaining = cap.map_or(usize::MAX, |cap| cap.saturating_sub(page.offset));
+                if page.exhausted || remaining == 0 {
+                    return Ok::<_, IgdbError>(None);
+                }
+
+                let limit = remaining.min(MAX_PAGE_SIZE);
+                let results: Vec<T> = self
+                    .query(endpoint, query.limit(limit).offset(page.offset))
                     .await?;
 
-            self.access_token = auth_response.access_token;
-            self.token_expiry =
-                Utc::now().naive_utc() + Duration::seconds(auth_response.expires_in.into());
+                let next_page = Page {
+                    offset: page.offset + results.len(),
+                    exhausted: results.len() < limit,
+                };
+                if !next_page.exhausted && cap == Some(next_page.offset) {
+                    warn!(
+                        "Query against {endpoint} hit the cap of {} results, remaining results \
+                         were not fetched",
+                        next_page.offset
+                    );
+                }
+
+                Ok(Some((stream::iter(results.into_iter().map(Ok)), next_page)))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Stream every result of a query against the endpoint that serves `T`
+    pub fn paginate_resource<'a, T>(
+        &'a self,
+        query: ApicalypseQuery,
+        cap: Option<usize>,
+    ) -> BoxStream<'a, IgdbResult<T>>
+    where
+        T: IgdbResource + Send + 'a,
+    {
+        self.paginate(T::ENDPOINT, query, cap)
+    }
+
+    /// Run up to [`MultiQuery::MAX_QUERIES`] queries in a single rate-limited request
+    pub async fn multiquery(&self, query: MultiQuery) -> IgdbResult<MultiQueryResponse> {
+        if query.len() > MultiQuery::MAX_QUERIES {
+            return Err(IgdbError::TooManySubQueries(query.len()));
         }
 
-        let request_delay = self.next_request - Utc::now().naive_utc();
+        self.post(MULTIQUERY_ENDPOINT, query).await
+    }
+
+    /// Send an Apicalypse query to the given IGDB endpoint, retrying with backoff when IGDB rate
+    /// limits us or fails, and re-authenticating once if IGDB rejects our access token
+    async fn post<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: impl Display,
+    ) -> IgdbResult<T> {
+        let url = self.endpoint_url(endpoint);
+        let body = query.to_string();
+
+        let mut access_token = self.access_token().await?;
+        let mut reauthenticated = false;
+        let mut attempt = 0;
+
+        loop {
+            // Held until the response body has been read, as that's when IGDB considers the
+            // request finished
+            let permit = self.rate_limiter.acquire().await;
+
+            let response = match self.send(url.clone(), body.clone(), &access_token).await {
+                Ok(response) => response,
+                Err(error)
+                    if RetryPolicy::is_retryable_error(&error)
+                        && self.retry_policy.should_retry(attempt) =>
+                {
+                    drop(permit);
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!("IGDB request failed, retrying in {delay:?}: {error}");
+                    sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            let status = response.status();
+
+            // The token can be revoked before its expiry, in which case we fetch a new one
+            if status == StatusCode::UNAUTHORIZED && !reauthenticated {
+                drop(permit);
+                warn!("IGDB rejected our access token, re-authenticating");
+                access_token = self.reauthenticate(&access_token).await?;
+                reauthenticated = true;
+                continue;
+            }
+
+            if RetryPolicy::is_retryable_status(status) && self.retry_policy.should_retry(attempt) {
+                drop(permit);
+                let delay = self.retry_policy.delay_for_response(&response, attempt);
+                warn!("IGDB responded with {status}, retrying in {delay:?}");
+                sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let body = response.error_for_status()?.bytes().await?;
 
-        if request_delay.num_seconds() > 0 {
-            // FIXME(Dan): Could remove this unwrap, but if this error happens then the world might be collapsing
-            sleep(request_delay.to_std().unwrap()).await;
+            return serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(
+                &body,
+            ))
+            .map_err(|error| IgdbError::Deserialize {
+                path: format!("{endpoint}{}", error.path()),
+                message: error.inner().to_string(),
+            });
         }
+    }
 
-        let response = self
-            .client
-            .post(IGDB_GAMES_URL)
-            .body(apicalypse_query.to_string())
+    /// Make a single request to IGDB. Callers must hold a rate limit permit
+    async fn send(
+        &self,
+        url: Url,
+        body: String,
+        access_token: &str,
+    ) -> Result<Response, reqwest::Error> {
+        self.client
+            .post(url)
+            .body(body)
             .header("Client-ID", &self.client_id)
-            .bearer_auth(&self.access_token)
+            .bearer_auth(access_token)
             .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
+            .await
+    }
+
+    /// Get a valid access token, refreshing it first if it has expired
+    async fn access_token(&self) -> IgdbResult<String> {
+        let mut access_token = self.access_token.lock().await;
 
-        self.next_request += TimeDelta::milliseconds(REQUEST_DELAY_MS);
+        match access_token.as_ref() {
+            None => Err(IgdbError::Offline),
+            Some(token) if token.expiry >= Utc::now().naive_utc() => Ok(token.token.clone()),
+            Some(_) => {
+                let token = self.fetch_access_token_for_request().await?;
+                let token_string = token.token.clone();
+                *access_token = Some(token);
+                Ok(token_string)
+            }
+        }
+    }
 
-        Ok(response)
+    /// Replace an access token that IGDB has rejected. If another request has already replaced
+    /// it, the newer token is used rather than fetching yet another one
+    async fn reauthenticate(&self, rejected_token: &str) -> IgdbResult<String> {
+        let mut access_token = self.access_token.lock().await;
+
+        if let Some(token) = access_token.as_ref() {
+            if token.token != rejected_token {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let token = self.fetch_access_token_for_request().await?;
+        let token_string = token.token.clone();
+        *access_token = Some(token);
+        Ok(token_string)
+    }
+
+    /// Request a new access token while serving a request. Failing takes the client offline, and
+    /// only the background task brings it back, so it is woken to retry on its own schedule
+    async fn fetch_access_token_for_request(&self) -> IgdbResult<AccessToken> {
+        let result = self.fetch_access_token().await;
+        if result.is_err() {
+            self.auth_failed.notify_one();
+        }
+        result
+    }
+
+    /// Request a new access token from Twitch, updating the online status to match the outcome
+    async fn fetch_access_token(&self) -> IgdbResult<AccessToken> {
+        let result = Self::refresh_access_token(
+            &self.client,
+            &self.oauth_url,
+            &self.client_id,
+            &self.client_secret,
+        )
+        .await;
+
+        self.online.store(result.is_ok(), Ordering::SeqCst);
+
+        let auth_response = result?;
+
+        Ok(AccessToken {
+            token: auth_response.access_token,
+            expiry: Utc::now().naive_utc() + Duration::seconds(auth_response.expires_in.into()),
+        })
+    }
+
+    /// Resolve an IGDB endpoint (e.g. `games`) against the configured base URL, regardless of
+    /// whether the base URL has a trailing slash
+    fn endpoint_url(&self, endpoint: &str) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            // Only fails for cannot-be-a-base URLs, which can never be valid HTTP base URLs
+            .expect("IGDB base URL must be a valid base URL")
+            .pop_if_empty()
+            .extend(endpoint.split('/'));
+        url
     }
 
     async fn refresh_access_token(
         client: &Client,
+        oauth_url: &Url,
         client_id: &str,
         client_secret: &str,
-    ) -> IgdbResult<TwitchAuthResponse> {
+    ) -> Result<TwitchAuthResponse, reqwest::Error> {
         client
-            .post(
-                TWITCH_OAUTH2_ENDPOINT
-                    .replace("{client_id}", client_id)
-                    .replace("{client_secret}", client_secret),
-            )
+            .post(oauth_url.clone())
+            .query(&[
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+                ("grant_type", "client_credentials"),
+            ])
             .send()
             .await?
             .error_for_status()?
@@ -110,3 +400,223 @@ impl IgdbClient {
             .await
     }
 }
+
+#[async_trait]
+impl IgdbApi for IgdbClient {
+    fn status(&self) -> IgdbStatus {
+        if self.online.load(Ordering::SeqCst) {
+            IgdbStatus::Online
+        } else {
+            IgdbStatus::Offline
+        }
+    }
+
+    async fn search(&self, search: GameSearch) -> IgdbResult<Vec<IgdbGame>> {
+        let apicalypse_query = search_query(search, self.search_sort.as_ref());
+
+        let games: Vec<IgdbGame> = self
+            .paginate_resource(apicalypse_query, Some(self.search_result_cap))
+            .try_collect()
+            .await?;
+
+        Ok(games)
+    }
+
+    async fn games_by_ids(&self, ids: Vec<i32>) -> IgdbResult<Vec<IgdbGame>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let apicalypse_query = ApicalypseQuery::builder()
+            .fields(IgdbGame::fields())
+            .r#where(field("id").contains_any(ids.iter().copied()))
+            .limit(ids.len());
+
+        self.query_resource(apicalypse_query).await
+    }
+
+    async fn raw_query(&self, endpoint: &str, query: ApicalypseQuery) -> IgdbResult<Value> {
+        self.post(endpoint, query).await
+    }
+}
+
+/// The query that fetches the games matching a search, sorted by its own sort or else by
+/// `default_sort`.
+///
+/// IGDB can't sort the results of `search`, so a sorted search instead matches games whose name
+/// contains the query. This deliberately trades IGDB's matching of alternative names and its
+/// relevance ranking for control over which games make the cut when a search has more results
+/// than are fetched
+fn search_query(search: GameSearch, default_sort: Option<&Sort>) -> ApicalypseQuery {
+    let apicalypse_query = ApicalypseQuery::builder()
+        .fields(IgdbGame::fields())
+        // Only main-games (exclude DLCs etc.)
+        .r#where(field("category").eq(0))
+        // As above, in case of upstream incorrect metadata
+        .and_where(field("parent_game").is_null())
+        // Exclude versions of games
+        .and_where(field("version_parent").is_null());
+
+    let apicalypse_query = match search.filter {
+        Some(filter) => apicalypse_query.and_where(filter),
+        None => apicalypse_query,
+    };
+
+    match search.sort.as_ref().or(default_sort) {
+        Some(sort) => apicalypse_query
+            .and_where(field("name").contains_text_ignore_case(&search.query))
+            .sort(&sort.field, sort.direction),
+        None => apicalypse_query.search(&search.query),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::json;
+
+    use super::*;
+    use crate::configuration::{RateLimit, RateLimitCoordination, Retry};
+
+    /// The clauses of a rendered query other than its (long) field list
+    fn clauses(query: ApicalypseQuery) -> Vec<String> {
+        query
+            .to_string()
+            .lines()
+            .filter(|line| !line.starts_with("fields "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    const MAIN_GAMES: &str = "category = 0 & parent_game = null & version_parent = null";
+
+    #[test]
+    fn searches_by_relevance_without_a_sort() {
+        assert_eq!(
+            clauses(search_query(GameSearch::new("Halo"), None)),
+            [
+                "search \"Halo\";".to_string(),
+                format!("where {MAIN_GAMES};")
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_names_when_sorting() {
+        let default_sort = Sort {
+            field: "total_rating_count".to_string(),
+            direction: SortDirection::Desc,
+        };
+
+        assert_eq!(
+            clauses(search_query(GameSearch::new("Halo"), Some(&default_sort))),
+            [
+                format!("where {MAIN_GAMES} & name ~ *\"Halo\"*;"),
+                "sort total_rating_count desc;".to_string(),
+            ]
+        );
+        assert_eq!(
+            clauses(search_query(
+                GameSearch::new("Halo").sort("first_release_date", SortDirection::Asc),
+                Some(&default_sort)
+            )),
+            [
+                format!("where {MAIN_GAMES} & name ~ *\"Halo\"*;"),
+                "sort first_release_date asc;".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_search_filters() {
+        let search = GameSearch::new("Halo").filter(field("aggregated_rating").ge(80));
+
+        assert_eq!(
+            clauses(search_query(search, None)),
+            [
+                "search \"Halo\";".to_string(),
+                format!("where {MAIN_GAMES} & aggregated_rating >= 80;"),
+            ]
+        );
+    }
+
+    /// A client of a fake Twitch token endpoint that fails as many requests as `failures` holds
+    async fn client_of_flaky_twitch(failures: Arc<AtomicUsize>) -> Arc<IgdbClient> {
+        let twitch = Router::new().route(
+            "/token",
+            post(move || async move {
+                let failing = failures
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok();
+                if failing {
+                    Err(StatusCode::SERVICE_UNAVAILABLE)
+                } else {
+                    Ok(Json(
+                        json!({ "access_token": "token", "expires_in": 5_000_000 }),
+                    ))
+                }
+            }),
+        );
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(twitch.into_make_service());
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let twitch = Twitch {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            oauth_url: format!("http://{address}/token").parse().unwrap(),
+        };
+        let igdb = Igdb {
+            base_url: format!("http://{address}/v4").parse().unwrap(),
+            search_result_cap: MAX_PAGE_SIZE,
+            search_sort: None,
+            retry: Retry {
+                max_attempts: 1,
+                base_delay_ms: 0,
+                max_delay_ms: 0,
+            },
+            rate_limit: RateLimit {
+                requests_per_second: 4.0,
+                burst: 4,
+                max_concurrent: 8,
+                coordination: RateLimitCoordination::Local,
+            },
+        };
+        Arc::new(IgdbClient::new(
+            &twitch,
+            &igdb,
+            &DatabaseConnection::Disconnected,
+        ))
+    }
+
+    async fn wait_for_status(client: &IgdbClient, status: IgdbStatus) {
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while client.status() != status {
+                sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("IGDB never became {status:?}"));
+    }
+
+    #[tokio::test]
+    async fn comes_back_online_after_a_request_fails_to_reauthenticate() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let client = client_of_flaky_twitch(failures.clone()).await;
+        tokio::spawn(client.clone().keep_authenticated());
+        wait_for_status(&client, IgdbStatus::Online).await;
+
+        // IGDB rejected the token, and Twitch fails to replace it once
+        failures.store(1, Ordering::SeqCst);
+        assert!(client.reauthenticate("token").await.is_err());
+        assert_eq!(client.status(), IgdbStatus::Offline);
+
+        // Long before the token it has would need refreshing
+        wait_for_status(&client, IgdbStatus::Online).await;
+        assert_eq!(failures.load(Ordering::SeqCst), 0);
+    }
+}
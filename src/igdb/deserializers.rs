@@ -1,5 +1,5 @@
 use chrono::{DateTime, NaiveDateTime};
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
 pub fn deserialize_artworks<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
@@ -37,13 +37,18 @@ pub fn deserialize_unix_timestamp<'de, D>(
 where
     D: Deserializer<'de>,
 {
-    let unix_timestamp = <Option<u32>>::deserialize(deserializer)?;
+    // Signed, as some companies and platforms predate the epoch
+    let unix_timestamp = <Option<i64>>::deserialize(deserializer)?;
 
-    Ok(unix_timestamp.map(|unix_timestamp| {
-        DateTime::from_timestamp(unix_timestamp.into(), 0)
-            .unwrap()
-            .naive_utc()
-    }))
+    unix_timestamp
+        .map(|unix_timestamp| {
+            DateTime::from_timestamp(unix_timestamp, 0)
+                .map(|date_time| date_time.naive_utc())
+                .ok_or_else(|| {
+                    de::Error::custom(format!("timestamp {unix_timestamp} is out of range"))
+                })
+        })
+        .transpose()
 }
 
 pub fn deserialize_franchise<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
@@ -58,89 +63,64 @@ where
     Ok(franchise.map(|franchise| franchise.name))
 }
 
-pub fn deserialize_platforms<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+pub fn deserialize_alternative_names<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     #[derive(Serialize, Deserialize)]
-    struct Platform {
+    struct AlternativeName {
         name: String,
     }
-    let platforms = <Option<Vec<Platform>>>::deserialize(deserializer)?;
-    Ok(platforms.map(|platforms| {
-        platforms
+    let alternative_names = <Option<Vec<AlternativeName>>>::deserialize(deserializer)?;
+    Ok(alternative_names.map(|alternative_names| {
+        alternative_names
             .into_iter()
             .map(|item| item.name)
             .collect::<Vec<String>>()
     }))
 }
 
-pub fn deserialize_genres<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+pub fn deserialize_supports_online_multiplayer<'de, D>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error>
 where
     D: Deserializer<'de>,
 {
     #[derive(Serialize, Deserialize)]
-    struct Genre {
-        name: String,
+    struct MultiplayerMetadata {
+        onlinecoop: bool,
     }
-    let genres = <Option<Vec<Genre>>>::deserialize(deserializer)?;
-    Ok(genres.map(|genres| {
-        genres
-            .into_iter()
-            .map(|item| item.name)
-            .collect::<Vec<String>>()
-    }))
+    let multiplayer_metadata = <Option<Vec<MultiplayerMetadata>>>::deserialize(deserializer)?;
+    Ok(multiplayer_metadata
+        .map(|multiplayer_metadata| multiplayer_metadata.into_iter().any(|item| item.onlinecoop)))
 }
 
-pub fn deserialize_game_modes<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    #[derive(Serialize, Deserialize)]
-    struct GameMode {
-        name: String,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Dated {
+        #[serde(deserialize_with = "deserialize_unix_timestamp", default)]
+        date: Option<NaiveDateTime>,
     }
-    let game_modes = <Option<Vec<GameMode>>>::deserialize(deserializer)?;
-    Ok(game_modes.map(|game_modes| {
-        game_modes
-            .into_iter()
-            .map(|item| item.name)
-            .collect::<Vec<String>>()
-    }))
-}
 
-pub fn deserialize_themes<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    #[derive(Serialize, Deserialize)]
-    struct Theme {
-        name: String,
+    #[test]
+    fn deserializes_unix_timestamps() {
+        let dated: Dated = serde_json::from_str(r#"{"date": -86400}"#).unwrap();
+        assert_eq!(dated.date.unwrap().to_string(), "1969-12-31 00:00:00");
+
+        let dated: Dated = serde_json::from_str(r#"{"date": null}"#).unwrap();
+        assert_eq!(dated.date, None);
     }
-    let themes = <Option<Vec<Theme>>>::deserialize(deserializer)?;
-    Ok(themes.map(|themes| {
-        themes
-            .into_iter()
-            .map(|item| item.name)
-            .collect::<Vec<String>>()
-    }))
-}
 
-pub fn deserialize_supports_online_multiplayer<'de, D>(
-    deserializer: D,
-) -> Result<Option<bool>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    #[derive(Serialize, Deserialize)]
-    struct MultiplayerMetadata {
-        onlinecoop: bool,
+    #[test]
+    fn rejects_out_of_range_timestamps() {
+        let error = serde_json::from_str::<Dated>(r#"{"date": 9223372036854775807}"#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("out of range"));
     }
-    let multiplayer_metadata = <Option<Vec<MultiplayerMetadata>>>::deserialize(deserializer)?;
-    Ok(multiplayer_metadata.map(|multiplayer_metadata| {
-        multiplayer_metadata
-            .into_iter()
-            .map(|item| item.onlinecoop)
-            .any(|next| next)
-    }))
 }
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum IgdbError {
+    #[error("HTTP Error {0:?}")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("Not authenticated with Twitch, IGDB is unavailable")]
+    Offline,
+
+    #[error("Multiquery has {0} sub-queries, but IGDB accepts at most 10")]
+    TooManySubQueries(usize),
+
+    #[error("Multiquery response is missing sub-query '{0}'")]
+    MissingSubQuery(String),
+
+    #[error("Could not deserialize IGDB response at {path}: {message}")]
+    Deserialize { path: String, message: String },
+}
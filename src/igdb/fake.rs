@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use super::apicalypse::ApicalypseQuery;
+use super::{GameSearch, IgdbApi, IgdbError, IgdbGame, IgdbResult, IgdbStatus};
+
+/// A request that was made against a [`FakeIgdb`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FakeRequest {
+    Search(GameSearch),
+    GamesByIds(Vec<i32>),
+    /// A raw query, rendered as Apicalypse
+    RawQuery {
+        endpoint: String,
+        query: String,
+    },
+}
+
+/// An in-memory stand-in for IGDB, serving a fixed set of games and recording every request made
+/// against it so that tests can assert on when igdbc goes upstream
+#[derive(Default)]
+pub struct FakeIgdb {
+    games: Vec<IgdbGame>,
+    requests: Mutex<Vec<FakeRequest>>,
+    offline: AtomicBool,
+}
+
+impl FakeIgdb {
+    pub fn new(games: Vec<IgdbGame>) -> Self {
+        Self {
+            games,
+            requests: Mutex::default(),
+            offline: AtomicBool::new(false),
+        }
+    }
+
+    /// Simulate losing (or regaining) the connection to Twitch. Requests made while offline fail
+    /// with [`IgdbError::Offline`]
+    pub fn set_status(&self, status: IgdbStatus) {
+        self.offline
+            .store(status == IgdbStatus::Offline, Ordering::SeqCst);
+    }
+
+    /// Every request made so far, oldest first
+    pub async fn requests(&self) -> Vec<FakeRequest> {
+        self.requests.lock().await.clone()
+    }
+
+    pub async fn request_count(&self) -> usize {
+        self.requests.lock().await.len()
+    }
+}
+
+#[async_trait]
+impl IgdbApi for FakeIgdb {
+    fn status(&self) -> IgdbStatus {
+        if self.offline.load(Ordering::SeqCst) {
+            IgdbStatus::Offline
+        } else {
+            IgdbStatus::Online
+        }
+    }
+
+    async fn search(&self, search: GameSearch) -> IgdbResult<Vec<IgdbGame>> {
+        if self.status() == IgdbStatus::Offline {
+            return Err(IgdbError::Offline);
+        }
+
+        let needle = search.query.to_lowercase();
+        self.requests.lock().await.push(FakeRequest::Search(search));
+
+        Ok(self
+            .games
+            .iter()
+            .filter(|game| game.name.to_lowercase().contains(&needle))
+            .cloned()
+            .collect())
+    }
+
+    async fn games_by_ids(&self, ids: Vec<i32>) -> IgdbResult<Vec<IgdbGame>> {
+        if self.status() == IgdbStatus::Offline {
+            return Err(IgdbError::Offline);
+        }
+
+        let games = self
+            .games
+            .iter()
+            .filter(|game| ids.contains(&game.id))
+            .cloned()
+            .collect();
+        self.requests
+            .lock()
+            .await
+            .push(FakeRequest::GamesByIds(ids));
+
+        Ok(games)
+    }
+
+    /// Always returns no results, as the fake only models games
+    async fn raw_query(&self, endpoint: &str, query: ApicalypseQuery) -> IgdbResult<Value> {
+        if self.status() == IgdbStatus::Offline {
+            return Err(IgdbError::Offline);
+        }
+
+        self.requests.lock().await.push(FakeRequest::RawQuery {
+            endpoint: endpoint.to_string(),
+            query: query.to_string(),
+        });
+
+        Ok(Value::Array(Vec::new()))
+    }
+}
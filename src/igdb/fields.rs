@@ -0,0 +1,291 @@
+//! Derives the fields to request from IGDB from the shape of a model's [`Deserialize`]
+//! implementation, so that adding a field (or expanding a reference into a struct) to a model
+//! automatically adds it to every query for that model.
+//!
+//! The model is "deserialized" from a [`FieldTracer`], which presents every struct as containing
+//! all of its fields and every sequence as containing a single element, and records the path of
+//! each value that is read. Nested structs, including those only seen by `deserialize_with`
+//! helpers, become dotted paths such as `cover.url`.
+
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::Deserialize;
+
+/// The dotted path of every field `T` reads, in declaration order
+///
+/// # Panics
+///
+/// If `T` can't be traced, i.e. it contains a type that only supports self-describing formats
+/// (such as `serde_json::Value`) or an enum. This is a mistake in the model rather than
+/// something that can happen at runtime
+pub fn field_paths<'de, T: Deserialize<'de>>() -> Vec<String> {
+    let paths = RefCell::new(Vec::new());
+
+    if let Err(error) = T::deserialize(FieldTracer {
+        path: None,
+        paths: &paths,
+    }) {
+        panic!(
+            "Could not derive IGDB fields for {}: {error}",
+            std::any::type_name::<T>()
+        );
+    }
+
+    paths.into_inner()
+}
+
+#[derive(Debug)]
+struct TraceError(String);
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: Display>(message: T) -> Self {
+        Self(message.to_string())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FieldTracer<'a> {
+    /// The path of the value being deserialized, or `None` for the model itself
+    path: Option<&'a str>,
+    paths: &'a RefCell<Vec<String>>,
+}
+
+impl FieldTracer<'_> {
+    fn record(&self) {
+        if let Some(path) = self.path {
+            let mut paths = self.paths.borrow_mut();
+            if !paths.iter().any(|recorded| recorded == path) {
+                paths.push(path.to_string());
+            }
+        }
+    }
+}
+
+/// Implement leaf deserializer methods by recording the current path and visiting a placeholder
+macro_rules! trace_leaf {
+    ($($method:ident => $visit:ident($($value:expr)?)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.record();
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for FieldTracer<'_> {
+    type Error = TraceError;
+
+    trace_leaf! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+        deserialize_unit => visit_unit(),
+        deserialize_ignored_any => visit_unit(),
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom(format!(
+            "`{}` does not describe its own type",
+            self.path.unwrap_or("<root>")
+        )))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(SingleElement { tracer: Some(self) })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(StructFields {
+            tracer: self,
+            fields: fields.iter(),
+            current: None,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+}
+
+struct SingleElement<'a> {
+    tracer: Option<FieldTracer<'a>>,
+}
+
+impl<'de> SeqAccess<'de> for SingleElement<'_> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.tracer
+            .take()
+            .map(|tracer| seed.deserialize(tracer))
+            .transpose()
+    }
+}
+
+struct StructFields<'a> {
+    tracer: FieldTracer<'a>,
+    fields: std::slice::Iter<'static, &'static str>,
+    current: Option<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for StructFields<'_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.current = self.fields.next().copied();
+        self.current
+            .map(|field| seed.deserialize(field.into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let field = self
+            .current
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+        let path = match self.tracer.path {
+            Some(parent) => format!("{parent}.{field}"),
+            None => field.to_string(),
+        };
+
+        seed.deserialize(FieldTracer {
+            path: Some(&path),
+            paths: self.tracer.paths,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::igdb::resources::{Company, ReleaseDate};
+    use crate::igdb::IgdbGame;
+
+    #[test]
+    fn traces_expanded_fields_through_deserializers() {
+        assert_eq!(
+            field_paths::<IgdbGame>(),
+            [
+                "id",
+                "name",
+                "alternative_names.name",
+                "summary",
+                "aggregated_rating",
+                "total_rating_count",
+                "hypes",
+                "themes.id",
+                "themes.name",
+                "url",
+                "artworks.url",
+                "cover.url",
+                "first_release_date",
+                "franchise.name",
+                "genres.id",
+                "genres.name",
+                "game_modes.id",
+                "game_modes.name",
+                "multiplayer_modes.onlinecoop",
+                "platforms.id",
+                "platforms.name",
+            ]
+        );
+    }
+
+    #[test]
+    fn traces_plain_resources() {
+        assert_eq!(
+            field_paths::<ReleaseDate>(),
+            ["id", "game", "platform", "date", "human", "region", "category"]
+        );
+        assert!(field_paths::<Company>().contains(&"published".to_string()));
+    }
+}
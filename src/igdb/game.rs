@@ -2,17 +2,29 @@ use chrono::NaiveDateTime;
 use serde::Deserialize;
 
 use super::deserializers::*;
+use super::resources::IgdbResource;
 
 #[derive(Deserialize, Clone)]
 pub struct IgdbGame {
     pub id: i32,
 
     pub name: String,
+
+    /// Other names the game is known by, e.g. abbreviations and regional titles
+    #[serde(deserialize_with = "deserialize_alternative_names", default)]
+    pub alternative_names: Option<Vec<String>>,
+
     pub summary: Option<String>,
     pub aggregated_rating: Option<f32>,
 
-    #[serde(deserialize_with = "deserialize_themes", default)]
-    pub themes: Option<Vec<String>>,
+    /// How many times users and critics have rated the game
+    pub total_rating_count: Option<i32>,
+
+    /// How many users followed the game before its release, IGDB's measure of its popularity
+    pub hypes: Option<i32>,
+
+    #[serde(default)]
+    pub themes: Option<Vec<IgdbReference>>,
 
     pub url: String,
 
@@ -28,11 +40,11 @@ pub struct IgdbGame {
     #[serde(deserialize_with = "deserialize_franchise", default)]
     pub franchise: Option<String>,
 
-    #[serde(deserialize_with = "deserialize_genres", default)]
-    pub genres: Option<Vec<String>>,
+    #[serde(default)]
+    pub genres: Option<Vec<IgdbReference>>,
 
-    #[serde(deserialize_with = "deserialize_game_modes", default)]
-    pub game_modes: Option<Vec<String>>,
+    #[serde(default)]
+    pub game_modes: Option<Vec<IgdbReference>>,
 
     #[serde(
         deserialize_with = "deserialize_supports_online_multiplayer",
@@ -41,6 +53,17 @@ pub struct IgdbGame {
     )]
     pub supports_online_multiplayer: Option<bool>,
 
-    #[serde(deserialize_with = "deserialize_platforms", default)]
-    pub platforms: Option<Vec<String>>,
+    #[serde(default)]
+    pub platforms: Option<Vec<IgdbReference>>,
+}
+
+/// A named resource that a game references, e.g. one of its platforms
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IgdbReference {
+    pub id: i32,
+    pub name: String,
+}
+
+impl IgdbResource for IgdbGame {
+    const ENDPOINT: &'static str = "games";
 }
@@ -1,26 +1,48 @@
-use client::IgdbClient;
-use lazy_static::lazy_static;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+use apicalypse::ApicalypseQuery;
 
 pub mod apicalypse;
 pub mod client;
 mod deserializers;
+pub mod error;
+pub mod fake;
+pub mod fields;
 mod game;
-pub use game::IgdbGame;
-use tokio::sync::Mutex;
-
-use crate::CONFIG;
+pub use error::IgdbError;
+pub use game::{IgdbGame, IgdbReference};
 mod models;
+pub mod multiquery;
+mod rate_limit;
+pub mod resources;
+mod retry;
+pub mod search;
+pub use search::GameSearch;
+
+pub type IgdbResult<T> = Result<T, IgdbError>;
+
+/// Whether igdbc can currently reach IGDB. While offline, games are served from the database only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IgdbStatus {
+    Online,
+    Offline,
+}
+
+/// The operations igdbc needs from IGDB. Implemented by [`client::IgdbClient`] for the real API
+/// and by [`fake::FakeIgdb`] for offline testing
+#[async_trait]
+pub trait IgdbApi: Send + Sync {
+    fn status(&self) -> IgdbStatus;
+
+    /// Search for main games (i.e. not DLCs or alternate versions) matching the given query
+    async fn search(&self, search: GameSearch) -> IgdbResult<Vec<IgdbGame>>;
+
+    /// Fetch the games with the given IGDB ids. Unknown ids are silently skipped
+    async fn games_by_ids(&self, ids: Vec<i32>) -> IgdbResult<Vec<IgdbGame>>;
 
-lazy_static! {
-    pub static ref IGDB_CLIENT: Mutex<IgdbClient> =
-        tokio::runtime::Runtime::new().unwrap().block_on(async {
-            Mutex::new(
-                IgdbClient::new(
-                    CONFIG.twitch.client_id.clone(),
-                    CONFIG.twitch.client_secret.clone(),
-                )
-                .await
-                .unwrap(),
-            )
-        });
+    /// Run an arbitrary query against an endpoint, returning IGDB's response untouched
+    async fn raw_query(&self, endpoint: &str, query: ApicalypseQuery) -> IgdbResult<Value>;
 }
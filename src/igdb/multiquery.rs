@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::apicalypse::literal::Quoted;
+use super::apicalypse::ApicalypseQuery;
+use super::{IgdbError, IgdbResult};
+
+/// A batch of named Apicalypse queries, possibly against different endpoints, sent to IGDB in a
+/// single request to the `multiquery` endpoint
+pub struct MultiQuery {
+    queries: Vec<SubQuery>,
+}
+
+struct SubQuery {
+    endpoint: String,
+    name: String,
+    count: bool,
+    query: ApicalypseQuery,
+}
+
+impl Display for MultiQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for query in &self.queries {
+            let suffix = if query.count { "/count" } else { "" };
+
+            writeln!(
+                f,
+                "query {}{} {} {{",
+                query.endpoint,
+                suffix,
+                Quoted(&query.name)
+            )?;
+            write!(f, "{}", query.query)?;
+            writeln!(f, "}};")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MultiQuery {
+    /// The maximum number of sub-queries IGDB accepts in a single multiquery
+    pub const MAX_QUERIES: usize = 10;
+
+    pub fn builder() -> Self {
+        Self {
+            queries: Vec::new(),
+        }
+    }
+
+    /// Add a sub-query returning the matching results of `endpoint`, retrievable from the response
+    /// with [`MultiQueryResponse::results`]
+    pub fn query(
+        mut self,
+        endpoint: impl ToString,
+        name: impl ToString,
+        query: ApicalypseQuery,
+    ) -> Self {
+        self.queries.push(SubQuery {
+            endpoint: endpoint.to_string(),
+            name: name.to_string(),
+            count: false,
+            query,
+        });
+        self
+    }
+
+    /// Add a sub-query returning the number of matching results of `endpoint`, retrievable from
+    /// the response with [`MultiQueryResponse::count`]
+    pub fn count(
+        mut self,
+        endpoint: impl ToString,
+        name: impl ToString,
+        query: ApicalypseQuery,
+    ) -> Self {
+        self.queries.push(SubQuery {
+            endpoint: endpoint.to_string(),
+            name: name.to_string(),
+            count: true,
+            query,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct SubQueryResponse {
+    name: String,
+    result: Option<Vec<Value>>,
+    count: Option<u64>,
+}
+
+/// The results of a [`MultiQuery`], keyed by sub-query name
+#[derive(Deserialize)]
+#[serde(from = "Vec<SubQueryResponse>")]
+pub struct MultiQueryResponse {
+    responses: HashMap<String, SubQueryResponse>,
+}
+
+impl From<Vec<SubQueryResponse>> for MultiQueryResponse {
+    fn from(responses: Vec<SubQueryResponse>) -> Self {
+        Self {
+            responses: responses
+                .into_iter()
+                .map(|response| (response.name.clone(), response))
+                .collect(),
+        }
+    }
+}
+
+impl MultiQueryResponse {
+    /// The results of the sub-query with the given name, deserialized as `T`
+    pub fn results<T: DeserializeOwned>(&self, name: &str) -> IgdbResult<Vec<T>> {
+        let results = self
+            .responses
+            .get(name)
+            .and_then(|response| response.result.as_ref())
+            .ok_or_else(|| IgdbError::MissingSubQuery(name.to_string()))?;
+
+        results
+            .iter()
+            .map(|result| {
+                serde_path_to_error::deserialize(result).map_err(|error| IgdbError::Deserialize {
+                    path: format!("{name}.{}", error.path()),
+                    message: error.inner().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// The number of results matched by the count sub-query with the given name
+    pub fn count(&self, name: &str) -> IgdbResult<u64> {
+        self.responses
+            .get(name)
+            .and_then(|response| response.count)
+            .ok_or_else(|| IgdbError::MissingSubQuery(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::igdb::apicalypse::filter::field;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Game {
+        id: i32,
+        name: String,
+    }
+
+    fn response() -> MultiQueryResponse {
+        serde_json::from_value(json!([
+            { "name": "Games", "result": [{ "id": 1, "name": "Halo" }, { "id": 2, "name": "Myst" }] },
+            { "name": "Game count", "count": 2 },
+            { "name": "Broken", "result": [{ "id": 3, "name": 3 }] },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_named_sub_queries() {
+        let games = ApicalypseQuery::builder()
+            .fields(vec!["name"])
+            .r#where(field("id").eq(1));
+        let multiquery = MultiQuery::builder()
+            .query("games", "Halo \"CE\"", games.clone())
+            .count("platforms", "Platform count", ApicalypseQuery::builder());
+
+        assert_eq!(multiquery.len(), 2);
+        assert_eq!(
+            multiquery.to_string(),
+            "query games \"Halo \\\"CE\\\"\" {\nfields name;\nwhere id = 1;\n};\n\
+             query platforms/count \"Platform count\" {\n};\n"
+        );
+        assert!(MultiQuery::builder().is_empty());
+    }
+
+    #[test]
+    fn parses_results_by_name() {
+        assert_eq!(
+            response().results::<Game>("Games").unwrap(),
+            [
+                Game {
+                    id: 1,
+                    name: "Halo".to_string()
+                },
+                Game {
+                    id: 2,
+                    name: "Myst".to_string()
+                },
+            ]
+        );
+        assert_eq!(response().count("Game count").unwrap(), 2);
+    }
+
+    #[test]
+    fn reports_missing_and_malformed_results() {
+        let response = response();
+
+        assert!(matches!(
+            response.results::<Game>("Platforms"),
+            Err(IgdbError::MissingSubQuery(name)) if name == "Platforms"
+        ));
+        // Results aren't counts, and counts aren't results
+        assert!(matches!(
+            response.count("Games"),
+            Err(IgdbError::MissingSubQuery(_))
+        ));
+        assert!(matches!(
+            response.results::<Game>("Game count"),
+            Err(IgdbError::MissingSubQuery(_))
+        ));
+        assert!(matches!(
+            response.results::<Game>("Broken"),
+            Err(IgdbError::Deserialize { path, .. }) if path == "Broken.name"
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use sea_orm::DatabaseConnection;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{sleep, Instant};
+use tracing::warn;
+
+use crate::configuration::{RateLimit, RateLimitCoordination};
+use crate::models::_entities::rate_limits;
+
+/// The name of IGDB's row in the shared `rate_limits` table
+const SHARED_RATE_LIMIT_NAME: &str = "igdb";
+
+/// Limits requests to IGDB using a token bucket for the request rate and a semaphore for the
+/// number of requests in flight, so that independent requests can run in parallel without
+/// exceeding IGDB's quota. The rate can optionally be shared with other instances through
+/// Postgres, in which case the local bucket is only used if the database is unavailable
+pub struct RateLimiter {
+    in_flight: Semaphore,
+    bucket: Mutex<TokenBucket>,
+    requests_per_second: f64,
+    burst: f64,
+    shared: Option<DatabaseConnection>,
+}
+
+struct TokenBucket {
+    /// Negative when requests have reserved tokens that have not been refilled yet
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Permission to make one request. The request counts as in flight until this is dropped
+pub struct RateLimitPermit<'a> {
+    _in_flight: SemaphorePermit<'a>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimit, db: &DatabaseConnection) -> Self {
+        let burst = f64::from(config.burst.max(1));
+        let shared = match config.coordination {
+            RateLimitCoordination::Local => None,
+            RateLimitCoordination::Postgres => Some(db.clone()),
+        };
+
+        Self {
+            in_flight: Semaphore::new(config.max_concurrent.max(1)),
+            bucket: Mutex::new(TokenBucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            requests_per_second: config.requests_per_second,
+            burst,
+            shared,
+        }
+    }
+
+    /// Wait until a request may be made. Tokens are reserved in arrival order, so waiters are
+    /// served first come, first served
+    pub async fn acquire(&self) -> RateLimitPermit<'_> {
+        let in_flight = self
+            .in_flight
+            .acquire()
+            .await
+            .expect("rate limiter semaphore is never closed");
+
+        let delay = match &self.shared {
+            Some(db) => self.reserve_shared(db).await,
+            None => self.reserve(),
+        };
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+
+        RateLimitPermit {
+            _in_flight: in_flight,
+        }
+    }
+
+    /// Reserve a request from the budget shared through Postgres, returning how long to wait
+    async fn reserve_shared(&self, db: &DatabaseConnection) -> Duration {
+        let emission_interval = Duration::from_secs_f64(1.0 / self.requests_per_second);
+
+        match rate_limits::Entity::reserve(
+            db,
+            SHARED_RATE_LIMIT_NAME,
+            emission_interval,
+            self.burst as u32,
+        )
+        .await
+        {
+            Ok(delay) => delay,
+            Err(error) => {
+                warn!("Could not reserve from shared rate limit, falling back to local: {error}");
+                self.reserve()
+            }
+        }
+    }
+
+    /// Take a token from the bucket, returning how long to wait until that token exists
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().expect("rate limiter mutex poisoned");
+
+        let now = Instant::now();
+        let refilled = (now - bucket.last_refill).as_secs_f64() * self.requests_per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.burst);
+        bucket.last_refill = now;
+
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.requests_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: f64, burst: u32, max_concurrent: usize) -> RateLimiter {
+        RateLimiter::new(
+            &RateLimit {
+                requests_per_second,
+                burst,
+                max_concurrent,
+                coordination: RateLimitCoordination::Local,
+            },
+            &DatabaseConnection::Disconnected,
+        )
+    }
+
+    fn assert_near(actual: Duration, expected: Duration) {
+        let tolerance = Duration::from_millis(20);
+        assert!(
+            actual <= expected && expected - actual < tolerance,
+            "expected about {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn allows_a_burst_then_spaces_requests_out() {
+        let limiter = limiter(4.0, 2, 8);
+
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_near(limiter.reserve(), Duration::from_millis(250));
+        assert_near(limiter.reserve(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn refills_up_to_the_burst() {
+        let limiter = limiter(4.0, 2, 8);
+        for _ in 0..3 {
+            limiter.reserve();
+        }
+
+        // Long enough to refill far more than the burst
+        limiter.bucket.lock().unwrap().last_refill -= Duration::from_secs(10);
+
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_near(limiter.reserve(), Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn limits_requests_in_flight() {
+        let limiter = limiter(1000.0, 1000, 1);
+
+        let permit = limiter.acquire().await;
+        let blocked = tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await;
+        assert!(blocked.is_err());
+
+        drop(permit);
+        let unblocked = tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await;
+        assert!(unblocked.is_ok());
+    }
+}
@@ -0,0 +1,189 @@
+//! Typed models for IGDB endpoints other than `games`. References to other resources are left
+//! as ids unless expanded by the query, so every field other than `id` is optional and only
+//! present if requested.
+
+use chrono::NaiveDateTime;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::deserializers::deserialize_unix_timestamp;
+use super::fields::field_paths;
+
+/// A model that can be fetched from an IGDB endpoint with
+/// [`IgdbClient::query_resource`](super::client::IgdbClient::query_resource)
+pub trait IgdbResource: DeserializeOwned {
+    /// The endpoint this resource is served from, relative to the IGDB base URL
+    const ENDPOINT: &'static str;
+
+    /// The fields to request so that every field of the model is populated
+    fn fields() -> Vec<String> {
+        field_paths::<Self>()
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Platform {
+    pub id: i32,
+    pub name: Option<String>,
+    pub abbreviation: Option<String>,
+    pub alternative_name: Option<String>,
+    pub slug: Option<String>,
+    pub generation: Option<i32>,
+    pub platform_family: Option<i32>,
+    pub summary: Option<String>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Platform {
+    const ENDPOINT: &'static str = "platforms";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Genre {
+    pub id: i32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Genre {
+    const ENDPOINT: &'static str = "genres";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Theme {
+    pub id: i32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Theme {
+    const ENDPOINT: &'static str = "themes";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Company {
+    pub id: i32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    /// ISO 3166-1 numeric country code
+    pub country: Option<i32>,
+    pub parent: Option<i32>,
+    pub developed: Option<Vec<i32>>,
+    pub published: Option<Vec<i32>>,
+    #[serde(deserialize_with = "deserialize_unix_timestamp", default)]
+    pub start_date: Option<NaiveDateTime>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Company {
+    const ENDPOINT: &'static str = "companies";
+}
+
+/// The involvement of a company in a game
+#[derive(Deserialize, Clone, Debug)]
+pub struct InvolvedCompany {
+    pub id: i32,
+    pub company: Option<i32>,
+    pub game: Option<i32>,
+    pub developer: Option<bool>,
+    pub publisher: Option<bool>,
+    pub porting: Option<bool>,
+    pub supporting: Option<bool>,
+}
+
+impl IgdbResource for InvolvedCompany {
+    const ENDPOINT: &'static str = "involved_companies";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Franchise {
+    pub id: i32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub games: Option<Vec<i32>>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Franchise {
+    const ENDPOINT: &'static str = "franchises";
+}
+
+/// A series of games
+#[derive(Deserialize, Clone, Debug)]
+pub struct Collection {
+    pub id: i32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub games: Option<Vec<i32>>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for Collection {
+    const ENDPOINT: &'static str = "collections";
+}
+
+/// The release of a game on a single platform in a single region
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReleaseDate {
+    pub id: i32,
+    pub game: Option<i32>,
+    pub platform: Option<i32>,
+    #[serde(deserialize_with = "deserialize_unix_timestamp", default)]
+    pub date: Option<NaiveDateTime>,
+    /// The date as a human readable string, e.g. "2018" or "Q3 2019" for imprecise dates
+    pub human: Option<String>,
+    pub region: Option<i32>,
+    pub category: Option<i32>,
+}
+
+impl IgdbResource for ReleaseDate {
+    const ENDPOINT: &'static str = "release_dates";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Website {
+    pub id: i32,
+    pub game: Option<i32>,
+    pub url: Option<String>,
+    /// The kind of website, e.g. official, wikia, steam
+    pub category: Option<i32>,
+    pub trusted: Option<bool>,
+}
+
+impl IgdbResource for Website {
+    const ENDPOINT: &'static str = "websites";
+}
+
+/// A game's identity on another service, e.g. its Steam app id
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExternalGame {
+    pub id: i32,
+    pub game: Option<i32>,
+    pub uid: Option<String>,
+    pub name: Option<String>,
+    /// The service this id belongs to, e.g. Steam or GOG
+    pub category: Option<i32>,
+    pub platform: Option<i32>,
+    pub url: Option<String>,
+}
+
+impl IgdbResource for ExternalGame {
+    const ENDPOINT: &'static str = "external_games";
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AgeRating {
+    pub id: i32,
+    /// The rating organisation, e.g. ESRB or PEGI
+    pub category: Option<i32>,
+    pub rating: Option<i32>,
+    pub synopsis: Option<String>,
+    pub content_descriptions: Option<Vec<i32>>,
+}
+
+impl IgdbResource for AgeRating {
+    const ENDPOINT: &'static str = "age_ratings";
+}
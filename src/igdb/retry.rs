@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+
+use crate::configuration::Retry;
+
+/// Decides whether and when a failed IGDB request should be retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &Retry) -> Self {
+        Self {
+            max_attempts: config.max_attempts.max(1),
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+        }
+    }
+
+    /// Whether another attempt may be made after `attempt` (zero-indexed) attempts have failed
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        attempt + 1 < self.max_attempts
+    }
+
+    /// Whether a response with this status is worth retrying, i.e. IGDB is rate limiting us or
+    /// is temporarily unwell
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    /// Whether a request that never got a response is worth retrying
+    pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect()
+    }
+
+    /// How long to wait before retrying a failed response, preferring IGDB's `Retry-After`
+    /// header over our own backoff. Either way, never longer than the configured maximum delay
+    pub fn delay_for_response(&self, response: &Response, attempt: u32) -> Duration {
+        self.delay_for_headers(response.headers(), attempt)
+    }
+
+    fn delay_for_headers(&self, headers: &HeaderMap, attempt: u32) -> Duration {
+        match retry_after(headers, Utc::now()) {
+            Some(delay) => delay.min(self.max_delay),
+            None => self.backoff(attempt),
+        }
+    }
+
+    /// Exponential backoff with full jitter, capped at the configured maximum delay
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        rand::thread_rng().gen_range(Duration::ZERO..=self.backoff_ceiling(attempt))
+    }
+
+    fn backoff_ceiling(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+/// Parse a `Retry-After` header, which can be either a number of seconds or an HTTP date
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(&Retry {
+            max_attempts: 4,
+            base_delay_ms: 250,
+            max_delay_ms: 10_000,
+        })
+    }
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum_delay() {
+        let policy = policy();
+        let ceilings: Vec<u64> = (0..8)
+            .map(|attempt| policy.backoff_ceiling(attempt).as_millis() as u64)
+            .collect();
+        assert_eq!(ceilings, [250, 500, 1000, 2000, 4000, 8000, 10_000, 10_000]);
+
+        assert_eq!(policy.backoff_ceiling(u32::MAX), Duration::from_secs(10));
+        for attempt in 0..8 {
+            assert!(policy.backoff(attempt) <= policy.backoff_ceiling(attempt));
+        }
+    }
+
+    #[test]
+    fn only_retries_up_to_the_maximum_attempts() {
+        let policy = policy();
+        assert!(policy.should_retry(2));
+        assert!(!policy.should_retry(3));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            retry_after(&headers(" 3 "), now),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:28:30 GMT"), now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:27:00 GMT"), now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&headers("soon"), now), None);
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn clamps_retry_after_to_the_maximum_delay() {
+        assert_eq!(
+            policy().delay_for_headers(&headers("86400"), 0),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            policy().delay_for_headers(&headers("2"), 0),
+            Duration::from_secs(2)
+        );
+    }
+}
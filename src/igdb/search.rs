@@ -0,0 +1,36 @@
+use super::apicalypse::filter::Filter;
+use super::apicalypse::{Sort, SortDirection};
+
+/// A search for games on IGDB, used to fill the cache
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameSearch {
+    pub query: String,
+    /// How IGDB should rank results before they are cut off. Falls back to the client's
+    /// configured default when unset
+    pub sort: Option<Sort>,
+    /// Conditions games must meet besides matching the query
+    pub filter: Option<Filter>,
+}
+
+impl GameSearch {
+    pub fn new(query: impl ToString) -> Self {
+        Self {
+            query: query.to_string(),
+            sort: None,
+            filter: None,
+        }
+    }
+
+    pub fn sort(mut self, field: impl ToString, direction: SortDirection) -> Self {
+        self.sort = Some(Sort {
+            field: field.to_string(),
+            direction,
+        });
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
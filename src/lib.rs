@@ -1,12 +1,18 @@
+use std::sync::Arc;
+
 use futures::future::try_join_all;
 use lazy_static::lazy_static;
 use models::_entities::{games, queries};
-use sea_orm::{ConnectionTrait, DatabaseConnection};
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
 use tracing::info;
 
-use crate::configuration::{get_config, Config};
+use crate::configuration::{get_config, Admin, Config, Search};
 use crate::error::IgdbcError;
-use crate::igdb::IGDB_CLIENT;
+use crate::igdb::{GameSearch, IgdbApi};
+use crate::models::games::GameFilter;
+use crate::routes::games::CursorSigner;
+use crate::routes::igdb::Passthrough;
+use crate::singleflight::SingleFlight;
 
 lazy_static! {
     pub static ref CONFIG: Config = get_config().unwrap();
@@ -18,30 +24,97 @@ pub mod error;
 pub mod igdb;
 pub mod models;
 pub mod routes;
+pub mod singleflight;
+
+type SharedSearchResult = Result<Vec<games::Model>, Arc<IgdbcError>>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct AppState {
     db: DatabaseConnection,
+    igdb: Arc<dyn IgdbApi>,
+    searches: Arc<SingleFlight<String, SharedSearchResult>>,
+    search: Arc<Search>,
+    cursors: Arc<CursorSigner>,
+    passthrough: Arc<Passthrough>,
+    admin: Arc<Admin>,
+}
+
+impl AppState {
+    pub fn new(db: DatabaseConnection, igdb: Arc<dyn IgdbApi>) -> Self {
+        Self {
+            db,
+            igdb,
+            searches: Arc::default(),
+            search: Arc::default(),
+            cursors: Arc::default(),
+            passthrough: Arc::default(),
+            admin: Arc::default(),
+        }
+    }
+
+    /// Configure how games are searched for in the database. Fuzzy search is off by default, and
+    /// cursors are signed with a random key
+    pub fn with_search(mut self, search: Search) -> Self {
+        self.cursors = Arc::new(CursorSigner::new(search.cursor_secret.as_deref()));
+        self.search = Arc::new(search);
+        self
+    }
+
+    /// Enable the admin endpoints, which reject every request by default
+    pub fn with_admin(mut self, admin: Admin) -> Self {
+        self.admin = Arc::new(admin);
+        self
+    }
+
+    /// Enable the raw Apicalypse endpoint, which rejects every request by default
+    pub fn with_passthrough(mut self, passthrough: Passthrough) -> Self {
+        self.passthrough = Arc::new(passthrough);
+        self
+    }
 }
 
-pub async fn search_igdb<C>(db: &C, query: String) -> Result<Vec<games::Model>, IgdbcError>
+/// Like [`search_igdb`], but concurrent searches for the same normalised query and filter share a
+/// single upstream request and database upsert
+pub async fn search_igdb_deduplicated(
+    state: &AppState,
+    query: String,
+    filter: GameFilter,
+) -> Result<Vec<games::Model>, IgdbcError> {
+    let key = filter.search_key(&query);
+    let db = state.db.clone();
+    let igdb = state.igdb.clone();
+
+    state
+        .searches
+        .run(key, move || async move {
+            search_igdb(&db, igdb.as_ref(), query, &filter)
+                .await
+                .map_err(Arc::new)
+        })
+        .await
+        .map_err(IgdbcError::Shared)
+}
+
+pub async fn search_igdb<C>(
+    db: &C,
+    igdb: &dyn IgdbApi,
+    query: String,
+    filter: &GameFilter,
+) -> Result<Vec<games::Model>, IgdbcError>
 where
-    C: ConnectionTrait,
+    C: ConnectionTrait + TransactionTrait,
 {
     info!("Refreshing game cache for query {query}");
 
-    let games;
-    {
-        let mut client = IGDB_CLIENT.lock().await;
-        games = client.search(query.clone()).await?;
-    }
+    let search = GameSearch::new(&query);
+    let search = match filter.igdb_filter() {
+        Some(igdb_filter) => search.filter(igdb_filter),
+        None => search,
+    };
+    let games = igdb.search(search).await?;
 
     info!("IGDB returned {} games!", games.len());
 
-    info!("Recording information about query");
-
-    queries::Entity::find_or_create(db, query).await?;
-
     let games = try_join_all(
         games
             .iter()
@@ -49,5 +122,14 @@ where
     )
     .await?;
 
-    Ok(games)
+    let game_ids: Vec<i32> = games.iter().map(|game| game.id).collect();
+    games::Entity::resolve_placeholder_references(db, &game_ids).await?;
+
+    // Only recorded once the games are cached, so that searches seeing the query as recent find
+    // them
+    info!("Recording information about query");
+
+    queries::Entity::find_or_create(db, filter.search_key(&query)).await?;
+
+    Ok(games::Entity::retain_matching(db, games, filter).await?)
 }
@@ -1,10 +1,12 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use axum::Server;
 use igdbc::CONFIG;
 
 use igdbc::error::IgdbcError;
+use igdbc::igdb::client::IgdbClient;
 use tokio::runtime;
 use tracing::Level;
 
@@ -25,9 +27,7 @@ fn main() -> Result<(), IgdbcError> {
  |_____\_____|_____/|____/ \_____|"#
     );
 
-    // Don't initialize igdb client immediately so can operate without a twitch connection in some situations
     lazy_static::initialize(&CONFIG);
-    lazy_static::initialize(&igdbc::igdb::IGDB_CLIENT);
 
     runtime::Builder::new_multi_thread()
         .enable_all()
@@ -37,7 +37,14 @@ fn main() -> Result<(), IgdbcError> {
 }
 
 async fn run() -> Result<(), IgdbcError> {
-    let app = igdbc::routes::app(&CONFIG.database_url).await?;
+    let db = igdbc::db::connect(&CONFIG.database_url).await?;
+
+    // Authenticate in the background so that igdbc can serve cached games while Twitch is
+    // unreachable
+    let igdb = Arc::new(IgdbClient::new(&CONFIG.twitch, &CONFIG.igdb, &db));
+    tokio::spawn(igdb.clone().keep_authenticated());
+
+    let app = igdbc::routes::app(db, igdb, &CONFIG);
     let addr = SocketAddr::from_str(&CONFIG.address).unwrap();
 
     Server::bind(&addr).serve(app.into_make_service()).await?;
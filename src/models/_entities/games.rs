@@ -12,19 +12,94 @@ pub struct Model {
     pub summary: Option<String>,
     #[sea_orm(column_type = "Float", nullable)]
     pub aggregated_rating: Option<f32>,
-    pub themes: Option<String>,
+    pub total_rating_count: Option<i32>,
+    pub hypes: Option<i32>,
     pub igdb_url: String,
     pub first_release_date: Option<DateTime>,
     pub franchise: Option<String>,
-    pub genres: Option<String>,
-    pub game_modes: Option<String>,
     pub supports_online_multiplayer: Option<bool>,
-    pub platforms: Option<String>,
     pub cover_art_url: Option<String>,
     pub artwork_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::game_aliases::Entity")]
+    GameAliases,
+    #[sea_orm(has_many = "super::game_game_modes::Entity")]
+    GameGameModes,
+    #[sea_orm(has_many = "super::game_genres::Entity")]
+    GameGenres,
+    #[sea_orm(has_many = "super::game_platforms::Entity")]
+    GamePlatforms,
+    #[sea_orm(has_many = "super::game_themes::Entity")]
+    GameThemes,
+}
+
+impl Related<super::game_aliases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameAliases.def()
+    }
+}
+
+impl Related<super::game_game_modes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameGameModes.def()
+    }
+}
+
+impl Related<super::game_genres::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameGenres.def()
+    }
+}
+
+impl Related<super::game_platforms::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GamePlatforms.def()
+    }
+}
+
+impl Related<super::game_themes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameThemes.def()
+    }
+}
+
+impl Related<super::game_modes::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::game_game_modes::Relation::GameModes.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::game_game_modes::Relation::Games.def().rev())
+    }
+}
+
+impl Related<super::genres::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::game_genres::Relation::Genres.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::game_genres::Relation::Games.def().rev())
+    }
+}
+
+impl Related<super::platforms::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::game_platforms::Relation::Platforms.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::game_platforms::Relation::Games.def().rev())
+    }
+}
+
+impl Related<super::themes::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::game_themes::Relation::Themes.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::game_themes::Relation::Games.def().rev())
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
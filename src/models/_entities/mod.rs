@@ -2,5 +2,17 @@
 
 pub mod prelude;
 
+pub mod game_aliases;
+pub mod game_game_modes;
+pub mod game_genres;
+pub mod game_modes;
+pub mod game_platforms;
+pub mod game_themes;
 pub mod games;
+pub mod genres;
+pub mod igdb_responses;
+pub mod platforms;
 pub mod queries;
+pub mod rate_limits;
+pub mod sea_orm_active_enums;
+pub mod themes;
@@ -1,4 +1,15 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::game_aliases::Entity as GameAliases;
+pub use super::game_game_modes::Entity as GameGameModes;
+pub use super::game_genres::Entity as GameGenres;
+pub use super::game_modes::Entity as GameModes;
+pub use super::game_platforms::Entity as GamePlatforms;
+pub use super::game_themes::Entity as GameThemes;
 pub use super::games::Entity as Games;
+pub use super::genres::Entity as Genres;
+pub use super::igdb_responses::Entity as IgdbResponses;
+pub use super::platforms::Entity as Platforms;
 pub use super::queries::Entity as Queries;
+pub use super::rate_limits::Entity as RateLimits;
+pub use super::themes::Entity as Themes;
@@ -0,0 +1,84 @@
+use sea_orm::{prelude::*, sea_query::OnConflict, ConnectionTrait, Set};
+use views::GameAliasDTO;
+
+use super::_entities::game_aliases::{ActiveModel, Column, Entity, Model};
+use super::_entities::games;
+use super::_entities::sea_orm_active_enums::AliasSource;
+
+impl Entity {
+    /// Replace the aliases of a game taken from its IGDB alternative names. Curated aliases are
+    /// left untouched
+    pub async fn replace_igdb_aliases<C>(
+        db: &C,
+        game_id: i32,
+        alternative_names: &[String],
+    ) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Self::delete_many()
+            .filter(Column::GameId.eq(game_id))
+            .filter(Column::Source.eq(AliasSource::Igdb))
+            .exec(db)
+            .await?;
+
+        let aliases = alternative_names
+            .iter()
+            .filter_map(|name| {
+                let searchable_alias = games::Entity::make_searchable_name(name.clone());
+                (!searchable_alias.is_empty()).then(|| ActiveModel {
+                    game_id: Set(game_id),
+                    alias: Set(name.clone()),
+                    searchable_alias: Set(searchable_alias),
+                    source: Set(AliasSource::Igdb),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if aliases.is_empty() {
+            return Ok(());
+        }
+
+        // Alternative names often differ only in punctuation or case
+        Self::insert_many(aliases)
+            .on_conflict(
+                OnConflict::columns([Column::GameId, Column::Source, Column::SearchableAlias])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_curated<C>(db: &C, game_id: i32, alias: String) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let active_model = ActiveModel {
+            game_id: Set(game_id),
+            searchable_alias: Set(games::Entity::make_searchable_name(alias.clone())),
+            alias: Set(alias),
+            source: Set(AliasSource::Curated),
+            ..Default::default()
+        };
+        active_model.insert(db).await
+    }
+}
+
+impl Model {
+    pub fn to_json(self) -> GameAliasDTO {
+        GameAliasDTO {
+            id: self.id,
+            game_id: self.game_id,
+            alias: self.alias,
+            source: match self.source {
+                AliasSource::Igdb => views::AliasSource::Igdb,
+                AliasSource::Curated => views::AliasSource::Curated,
+            },
+        }
+    }
+}
@@ -1,124 +1,947 @@
+use std::collections::HashSet;
+
 use super::_entities::games::{ActiveModel, Column, Entity, Model};
-use crate::igdb::IgdbGame;
-use migration::extension::postgres::PgExpr;
-use sea_orm::{prelude::*, ConnectionTrait, QuerySelect, Set, TryIntoModel};
+use super::_entities::{
+    game_aliases, game_game_modes, game_genres, game_modes, game_platforms, game_themes, genres,
+    platforms, themes,
+};
+use crate::configuration::{Ranking, Search};
+use crate::igdb::apicalypse::filter::{field, Filter};
+use crate::igdb::{IgdbGame, IgdbReference};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use itertools::{izip, Itertools};
+use sea_orm::sea_query::{Alias, NullOrdering, OnConflict, Query, SimpleExpr};
+use sea_orm::{
+    prelude::*, Condition, ConnectionTrait, DbBackend, FromQueryResult, LoaderTrait, Order,
+    QueryOrder, QueryResult, QuerySelect, Set, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
 use tracing::trace;
 use views::GameDTO;
 
+/// A kind of named resource that games reference many of, linked to games through a junction
+/// table
+struct Reference {
+    table: &'static str,
+    junction: &'static str,
+    foreign_key: &'static str,
+    /// The field of IGDB games holding them
+    igdb_field: &'static str,
+}
+
+impl Reference {
+    /// Whether a game has a reference of this kind named `name`, ignoring case
+    fn named(&self, name: &str) -> SimpleExpr {
+        Expr::cust_with_values(
+            format!(
+                r#"
+                games.id IN (
+                    SELECT link.game_id
+                    FROM {junction} AS link
+                    JOIN {table} AS reference ON reference.id = link.{foreign_key}
+                    WHERE lower(reference.name) = lower($1)
+                )
+                "#,
+                table = self.table,
+                junction = self.junction,
+                foreign_key = self.foreign_key,
+            ),
+            [name],
+        )
+    }
+}
+
+const PLATFORMS: Reference = Reference {
+    table: "platforms",
+    junction: "game_platforms",
+    foreign_key: "platform_id",
+    igdb_field: "platforms",
+};
+const GENRES: Reference = Reference {
+    table: "genres",
+    junction: "game_genres",
+    foreign_key: "genre_id",
+    igdb_field: "genres",
+};
+const THEMES: Reference = Reference {
+    table: "themes",
+    junction: "game_themes",
+    foreign_key: "theme_id",
+    igdb_field: "themes",
+};
+const GAME_MODES: Reference = Reference {
+    table: "game_modes",
+    junction: "game_game_modes",
+    foreign_key: "game_mode_id",
+    igdb_field: "game_modes",
+};
+
+/// The order games found by a search are listed in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameSort {
+    /// Most relevant to the query first
+    #[default]
+    Relevance,
+    /// Highest aggregated rating first
+    Rating,
+    /// Most recently released first
+    ReleaseDate,
+    /// Alphabetically by name
+    Name,
+}
+
+impl GameSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameSort::Relevance => "relevance",
+            GameSort::Rating => "rating",
+            GameSort::ReleaseDate => "release_date",
+            GameSort::Name => "name",
+        }
+    }
+}
+
+/// A game found by a search, with how relevant it is to the query
+pub struct RankedGame {
+    pub game: Model,
+    pub relevance: f64,
+}
+
+impl FromQueryResult for RankedGame {
+    fn from_query_result(row: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        Ok(Self {
+            game: Model::from_query_result(row, pre)?,
+            relevance: row.try_get(pre, "relevance")?,
+        })
+    }
+}
+
+impl RankedGame {
+    /// Where the game is in results listed by `sort`, to continue them after it
+    pub fn position(&self, sort: GameSort) -> SearchPosition {
+        let sort_key = match sort {
+            GameSort::Relevance => SortKey::Relevance,
+            GameSort::Rating => SortKey::Rating(self.game.aggregated_rating),
+            GameSort::ReleaseDate => SortKey::ReleaseDate(self.game.first_release_date),
+            GameSort::Name => SortKey::Name(self.game.name.clone()),
+        };
+
+        SearchPosition {
+            sort_key,
+            relevance: self.relevance,
+            id: self.game.id,
+        }
+    }
+}
+
+/// A place in the results of a search, by the sort key, relevance and id of a game listed
+/// there. Results continued from it neither repeat nor skip games, even if games are added to or
+/// removed from those before it in the meantime
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchPosition {
+    sort_key: SortKey,
+    #[serde(with = "float_bits")]
+    relevance: f64,
+    id: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SortKey {
+    Relevance,
+    Rating(#[serde(with = "float_bits::optional_f32")] Option<f32>),
+    ReleaseDate(Option<NaiveDateTime>),
+    Name(String),
+}
+
+/// Positions are compared for equality against the database, so their floats are serialised by
+/// their bits, as decimals may not parse back to exactly the same value
+mod float_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+
+    pub mod optional_f32 {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            value.map(f32::to_bits).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<u32>::deserialize(deserializer)?.map(f32::from_bits))
+        }
+    }
+}
+
+impl SearchPosition {
+    /// Whether a game comes after this position in results ordered by a sort column (unless
+    /// sorted by relevance), then `relevance` descending, then id
+    fn games_after(&self, relevance: SimpleExpr) -> Condition {
+        let ties_after = Condition::any()
+            .add(Expr::expr(relevance.clone()).lt(self.relevance))
+            .add(
+                Condition::all()
+                    .add(Expr::expr(relevance).eq(self.relevance))
+                    .add(Column::Id.gt(self.id)),
+            );
+
+        match &self.sort_key {
+            SortKey::Relevance => ties_after,
+            SortKey::Rating(rating) => {
+                Self::descending_after(Column::AggregatedRating, *rating, ties_after)
+            }
+            SortKey::ReleaseDate(date) => {
+                Self::descending_after(Column::FirstReleaseDate, *date, ties_after)
+            }
+            SortKey::Name(name) => Condition::any().add(Column::Name.gt(name.as_str())).add(
+                Condition::all()
+                    .add(Column::Name.eq(name.as_str()))
+                    .add(ties_after),
+            ),
+        }
+    }
+
+    /// After `value` in `column` sorted descending with nulls last, or tied and `ties_after`
+    fn descending_after<V>(column: Column, value: Option<V>, ties_after: Condition) -> Condition
+    where
+        V: Into<Value>,
+    {
+        match value {
+            Some(value) => {
+                let value = value.into();
+                Condition::any()
+                    .add(column.lt(value.clone()))
+                    .add(column.is_null())
+                    .add(Condition::all().add(column.eq(value)).add(ties_after))
+            }
+            None => Condition::all().add(column.is_null()).add(ties_after),
+        }
+    }
+}
+
+/// Conditions that games found by a search must meet. Names of platforms, genres, themes and game
+/// modes are matched ignoring case
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct GameFilter {
+    pub platform: Option<String>,
+    pub genre: Option<String>,
+    pub theme: Option<String>,
+    pub game_mode: Option<String>,
+    /// Released on or after this date
+    pub released_after: Option<NaiveDate>,
+    /// Released before this date
+    pub released_before: Option<NaiveDate>,
+    /// The lowest aggregated rating, from 0 to 100
+    pub min_rating: Option<f32>,
+    pub online_multiplayer: Option<bool>,
+}
+
+impl GameFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Why no game could meet this filter, if none could
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let Some(rating) = self.min_rating {
+            if !(0.0..=100.0).contains(&rating) {
+                return Err("min_rating must be between 0 and 100");
+            }
+        }
+        if let (Some(after), Some(before)) = (self.released_after, self.released_before) {
+            if after >= before {
+                return Err("released_after must be before released_before");
+            }
+        }
+        Ok(())
+    }
+
+    fn references(&self) -> [(Reference, Option<&str>); 4] {
+        [
+            (PLATFORMS, self.platform.as_deref()),
+            (GENRES, self.genre.as_deref()),
+            (THEMES, self.theme.as_deref()),
+            (GAME_MODES, self.game_mode.as_deref()),
+        ]
+    }
+
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+
+        for (reference, name) in self.references() {
+            if let Some(name) = name {
+                condition = condition.add(reference.named(name));
+            }
+        }
+        if let Some(date) = self.released_after {
+            condition = condition.add(Column::FirstReleaseDate.gte(date.and_time(NaiveTime::MIN)));
+        }
+        if let Some(date) = self.released_before {
+            condition = condition.add(Column::FirstReleaseDate.lt(date.and_time(NaiveTime::MIN)));
+        }
+        if let Some(rating) = self.min_rating {
+            condition = condition.add(Column::AggregatedRating.gte(rating));
+        }
+        match self.online_multiplayer {
+            Some(true) => condition = condition.add(Column::SupportsOnlineMultiplayer.eq(true)),
+            // Games IGDB has no multiplayer data for are stored as `NULL`
+            Some(false) => {
+                condition = condition.add(
+                    Column::SupportsOnlineMultiplayer
+                        .eq(false)
+                        .or(Column::SupportsOnlineMultiplayer.is_null()),
+                )
+            }
+            None => (),
+        }
+
+        condition
+    }
+
+    /// The same conditions for searching IGDB, or `None` if there are none. IGDB can't tell
+    /// games without online multiplayer from those it has no data for, so only requiring it is
+    /// passed on
+    pub fn igdb_filter(&self) -> Option<Filter> {
+        let mut filters = Vec::new();
+
+        for (reference, name) in self.references() {
+            if let Some(name) = name {
+                filters.push(field(format!("{}.name", reference.igdb_field)).eq_ignore_case(name));
+            }
+        }
+        if let Some(date) = self.released_after {
+            filters.push(field("first_release_date").ge(unix_timestamp(date)));
+        }
+        if let Some(date) = self.released_before {
+            filters.push(field("first_release_date").lt(unix_timestamp(date)));
+        }
+        if let Some(rating) = self.min_rating {
+            filters.push(field("aggregated_rating").ge(rating));
+        }
+        if self.online_multiplayer == Some(true) {
+            filters.push(field("multiplayer_modes.onlinecoop").eq(true));
+        }
+
+        filters
+            .into_iter()
+            .reduce(|filters, filter| filters & filter)
+    }
+
+    /// Identifies a search for `query` with this filter, e.g. among the queries made to IGDB.
+    /// Unfiltered searches are identified by the query alone
+    pub fn key(&self, query: &str) -> String {
+        let params = [
+            (
+                "platform",
+                self.platform.as_ref().map(|name| name.to_lowercase()),
+            ),
+            ("genre", self.genre.as_ref().map(|name| name.to_lowercase())),
+            ("theme", self.theme.as_ref().map(|name| name.to_lowercase())),
+            (
+                "game_mode",
+                self.game_mode.as_ref().map(|name| name.to_lowercase()),
+            ),
+            (
+                "released_after",
+                self.released_after.map(|date| date.to_string()),
+            ),
+            (
+                "released_before",
+                self.released_before.map(|date| date.to_string()),
+            ),
+            (
+                "min_rating",
+                self.min_rating.map(|rating| rating.to_string()),
+            ),
+            (
+                "online_multiplayer",
+                self.online_multiplayer.map(|online| online.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(param, value)| Some(format!("{param}={}", value?)))
+        .join("&");
+
+        if params.is_empty() {
+            query.to_string()
+        } else {
+            format!("{query}?{params}")
+        }
+    }
+
+    /// Identifies the IGDB search for `query` with this filter, which is the same for queries
+    /// naming the same games, e.g. "Halo: Reach" and "halo reach"
+    pub fn search_key(&self, query: &str) -> String {
+        self.key(&Entity::make_searchable_name(query.to_string()))
+    }
+}
+
+fn unix_timestamp(date: NaiveDate) -> i64 {
+    date.and_time(NaiveTime::MIN).and_utc().timestamp()
+}
+
 impl Entity {
-    const UNSEARCHABLE_CHARS: [char; 21] = [
-        '[', ']', '"', '\'', ':', ';', '!', '@', '#', '$', '%', '^', '&', '*', '(', ')', '-', '+',
-        '=', '~', ' ',
-    ];
+    /// Drop the games that don't meet `filter`, e.g. those that IGDB couldn't be asked to leave
+    /// out
+    pub async fn retain_matching<C>(
+        db: &C,
+        mut games: Vec<Model>,
+        filter: &GameFilter,
+    ) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if filter.is_empty() {
+            return Ok(games);
+        }
+
+        let matching: HashSet<i32> = Self::find()
+            .select_only()
+            .column(Column::Id)
+            .filter(Column::Id.is_in(games.iter().map(|game| game.id)))
+            .filter(filter.condition())
+            .into_tuple()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+
+        games.retain(|game| matching.contains(&game.id));
+
+        Ok(games)
+    }
+
+    /// Games meeting `filter` that match `query`: those whose name or one of whose aliases starts
+    /// with it, full-text matches and, if `search.fuzzy` is enabled, similarly named games.
+    /// Ordered by `sort`, ties broken by relevance and then id, and continuing `after` a position
+    /// in the same search if given
+    pub async fn search<C>(
+        db: &C,
+        query: String,
+        limit: usize,
+        after: Option<&SearchPosition>,
+        sort: GameSort,
+        filter: &GameFilter,
+        search: &Search,
+    ) -> Result<Vec<RankedGame>, DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let searchable_name = Self::make_searchable_name(query.clone());
+
+        // FIXME(Dan): Use gamename entity, serialise without special characters to make searching better.
+        let mut matches = Condition::any().add(Expr::cust_with_values(
+            "search_vector @@ websearch_to_tsquery('english', $1)",
+            [query.clone()],
+        ));
+        // Every name starts with an empty one, e.g. that of a query made only of punctuation
+        if !searchable_name.is_empty() {
+            matches = matches
+                .add(Column::SearchableName.starts_with(&searchable_name))
+                .add(Self::has_alias_starting_with(&searchable_name));
+        }
+        if search.fuzzy.enabled {
+            matches = matches.add(Expr::cust_with_values(
+                "searchable_name % $1",
+                [searchable_name.clone()],
+            ));
+        }
+
+        let relevance = Self::relevance(&query, &searchable_name, &search.ranking);
+        let mut select = Self::find()
+            .column_as(relevance.clone(), "relevance")
+            .filter(matches)
+            .filter(filter.condition());
+        if let Some(position) = after {
+            select = select.filter(position.games_after(relevance.clone()));
+        }
+        let select = match sort {
+            GameSort::Relevance => select,
+            GameSort::Rating => select.order_by_with_nulls(
+                Column::AggregatedRating,
+                Order::Desc,
+                NullOrdering::Last,
+            ),
+            GameSort::ReleaseDate => select.order_by_with_nulls(
+                Column::FirstReleaseDate,
+                Order::Desc,
+                NullOrdering::Last,
+            ),
+            GameSort::Name => select.order_by_asc(Column::Name),
+        }
+        .order_by_desc(relevance)
+        .order_by_asc(Column::Id)
+        .limit(limit as u64)
+        .into_model::<RankedGame>();
+
+        if !search.fuzzy.enabled {
+            return select.all(db).await;
+        }
+
+        let txn = db.begin().await?;
+
+        // The trigram index is only used by the `%` operator, whose threshold is a setting
+        // rather than an operand. Scope it to this transaction so pooled connections keep the
+        // default
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT set_config('pg_trgm.similarity_threshold', $1, true)",
+            [search.fuzzy.threshold.to_string().into()],
+        ))
+        .await?;
+
+        let games = select.all(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(games)
+    }
+
+    /// Whether a game has an alias starting with `searchable_alias`
+    fn has_alias_starting_with(searchable_alias: &str) -> SimpleExpr {
+        Column::Id.in_subquery(
+            Query::select()
+                .column(game_aliases::Column::GameId)
+                .from(game_aliases::Entity)
+                .and_where(game_aliases::Column::SearchableAlias.starts_with(searchable_alias))
+                .to_owned(),
+        )
+    }
+
+    /// How relevant a game is to `query`, as the sum of its matches and popularity weighted by
+    /// `ranking`
+    fn relevance(query: &str, searchable_name: &str, ranking: &Ranking) -> SimpleExpr {
+        Expr::cust_with_values(
+            r#"
+            $1 * (
+                searchable_name = $8
+                OR EXISTS (
+                    SELECT 1 FROM game_aliases
+                    WHERE game_aliases.game_id = games.id AND game_aliases.searchable_alias = $8
+                )
+            )::int
+            + $2 * (
+                starts_with(searchable_name, $8)
+                OR EXISTS (
+                    SELECT 1 FROM game_aliases
+                    WHERE game_aliases.game_id = games.id
+                        AND starts_with(game_aliases.searchable_alias, $8)
+                )
+            )::int
+            + $3 * coalesce(ts_rank(search_vector, websearch_to_tsquery('english', $9)), 0)
+            + $4 * similarity(searchable_name, $8)
+            + $5 * coalesce(aggregated_rating, 0) / 100
+            + $6 * ln(1 + coalesce(total_rating_count, 0))
+            + $7 * ln(1 + coalesce(hypes, 0))
+            "#,
+            [
+                ranking.exact_match.into(),
+                ranking.prefix_match.into(),
+                ranking.text_rank.into(),
+                ranking.similarity.into(),
+                ranking.rating.into(),
+                ranking.rating_count.into(),
+                ranking.popularity.into(),
+                Value::from(searchable_name),
+                Value::from(query),
+            ],
+        )
+    }
+
+    /// Store a game fetched from IGDB along with its aliases and references, atomically so that
+    /// readers never see it half-updated
+    pub async fn create_or_update<C>(db: &C, mut json: IgdbGame) -> Result<Model, DbErr>
+    where
+        C: TransactionTrait,
+    {
+        trace!("Creating/Updating game '{}'", json.name);
+        let id = json.id;
+        let references = [
+            (PLATFORMS, json.platforms.take()),
+            (GENRES, json.genres.take()),
+            (THEMES, json.themes.take()),
+            (GAME_MODES, json.game_modes.take()),
+        ];
+
+        let alternative_names = json.alternative_names.take().unwrap_or_default();
+
+        let txn = db.begin().await?;
+        let model = Self::save(&txn, json).await?;
+        Self::update_search_vector(&txn, id, &alternative_names).await?;
+        game_aliases::Entity::replace_igdb_aliases(&txn, id, &alternative_names).await?;
 
-    pub async fn find_by_query<C>(db: &C, query: String, limit: usize) -> Result<Vec<Model>, DbErr>
+        for (reference, items) in references {
+            Self::link_references(&txn, id, &reference, items.unwrap_or_default()).await?;
+        }
+        txn.commit().await?;
+
+        Ok(model)
+    }
+
+    /// Create the game, or update it if it's already stored. A single statement, so that
+    /// concurrent fetches of a new game don't both try to create it
+    async fn save<C>(db: &C, json: IgdbGame) -> Result<Model, DbErr>
     where
         C: ConnectionTrait,
     {
-        Self::find()
-            // FIXME(Dan): Use gamename entity, serialise without special characters to make searching better.
-            .filter(
-                Expr::col(Column::SearchableName)
-                    .ilike(format!("{}%", Self::make_searchable_name(query))),
+        trace!("Saving game '{}'", json.name);
+        Self::insert(Self::active_model(json))
+            .on_conflict(
+                OnConflict::column(Column::Id)
+                    .update_columns([
+                        Column::Name,
+                        Column::SearchableName,
+                        Column::Summary,
+                        Column::AggregatedRating,
+                        Column::TotalRatingCount,
+                        Column::Hypes,
+                        Column::IgdbUrl,
+                        Column::FirstReleaseDate,
+                        Column::Franchise,
+                        Column::SupportsOnlineMultiplayer,
+                    ])
+                    .to_owned(),
             )
-            .limit(limit as u64)
-            .all(db)
+            .exec_with_returning(db)
             .await
     }
 
-    pub async fn create_or_update<C>(db: &C, json: IgdbGame) -> Result<Model, DbErr>
+    /// Recompute the full-text search vector of a game, which ranks matches in its name above
+    /// those in its alternative names, and those above matches in its summary
+    async fn update_search_vector<C>(
+        db: &C,
+        id: i32,
+        alternative_names: &[String],
+    ) -> Result<(), DbErr>
     where
         C: ConnectionTrait,
     {
-        trace!("Creating/Updating game '{}'", json.name);
-        let maybe_model = Entity::find_by_id(json.id).one(db).await?;
-
-        if let Some(model) = maybe_model {
-            trace!("Game '{}' already exists - updating", json.name);
-            let mut active_model: ActiveModel = model.into();
-            active_model.id = Set(json.id);
-            active_model.name = Set(json.name);
-            active_model.summary = Set(json.summary);
-            active_model.aggregated_rating = Set(json.aggregated_rating);
-            active_model.themes = Set(json.themes.map(|themes| themes.join(",")));
-            active_model.igdb_url = Set(json.url);
-            active_model.first_release_date = Set(json.first_release_date);
-            active_model.franchise = Set(json.franchise);
-            active_model.genres = Set(json.genres.map(|genres| genres.join(",")));
-            active_model.game_modes = Set(json.game_modes.map(|game_modes| game_modes.join(",")));
-            active_model.supports_online_multiplayer = Set(json.supports_online_multiplayer);
-            active_model.platforms = Set(json.platforms.map(|platforms| platforms.join(",")));
-            active_model.save(db).await?.try_into_model()
-        } else {
-            trace!("Game '{}' does not exist - creating", json.name);
-            Self::create(db, json).await
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            UPDATE games
+            SET search_vector =
+                setweight(to_tsvector('english', name), 'A')
+                || setweight(to_tsvector('english', $2), 'B')
+                || setweight(to_tsvector('english', coalesce(summary, '')), 'C')
+            WHERE id = $1
+            "#,
+            [id.into(), alternative_names.join(" ").into()],
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store `items` and make them the only references of this kind the game has
+    async fn link_references<C>(
+        db: &C,
+        game_id: i32,
+        reference: &Reference,
+        mut items: Vec<IgdbReference>,
+    ) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let backend = db.get_database_backend();
+        let table = Alias::new(reference.table);
+        let junction = Alias::new(reference.junction);
+        let foreign_key = Alias::new(reference.foreign_key);
+
+        // Consistently ordered so that concurrent upserts lock rows in the same order
+        items.sort_by_key(|item| item.id);
+        items.dedup_by_key(|item| item.id);
+
+        if !items.is_empty() {
+            let mut upsert = Query::insert()
+                .into_table(table)
+                .columns([Alias::new("id"), Alias::new("name")])
+                .on_conflict(
+                    OnConflict::column(Alias::new("id"))
+                        .update_column(Alias::new("name"))
+                        .to_owned(),
+                )
+                .to_owned();
+            for item in &items {
+                upsert.values_panic([item.id.into(), item.name.clone().into()]);
+            }
+            db.execute(backend.build(&upsert)).await?;
+        }
+
+        let unlink = Query::delete()
+            .from_table(junction.clone())
+            .and_where(Expr::col(Alias::new("game_id")).eq(game_id))
+            .to_owned();
+        db.execute(backend.build(&unlink)).await?;
+
+        if !items.is_empty() {
+            let mut link = Query::insert()
+                .into_table(junction)
+                .columns([Alias::new("game_id"), foreign_key])
+                .on_conflict(OnConflict::new().do_nothing().to_owned())
+                .to_owned();
+            for item in &items {
+                link.values_panic([game_id.into(), item.id.into()]);
+            }
+            db.execute(backend.build(&link)).await?;
         }
+
+        Ok(())
+    }
+
+    /// Swap the placeholder (negative) ids of references back-filled from before they were
+    /// stored with their IGDB ids for the real ones, where a reference with the same name has
+    /// since been fetched from IGDB. Only the references of `game_ids`, the games just fetched,
+    /// are checked, as others were resolved when their games were fetched
+    pub async fn resolve_placeholder_references<C>(db: &C, game_ids: &[i32]) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if game_ids.is_empty() {
+            return Ok(());
+        }
+        let game_ids = game_ids.iter().join(", ");
+
+        for reference in [PLATFORMS, GENRES, THEMES, GAME_MODES] {
+            // Every statement sees the same snapshot, so links are copied before the cascading
+            // delete removes them
+            db.execute_unprepared(&format!(
+                r#"
+                WITH resolutions AS (
+                    SELECT placeholder.id AS placeholder_id, resolved.id AS resolved_id
+                    FROM {table} AS resolved
+                    JOIN {table} AS placeholder
+                        ON placeholder.name = resolved.name AND placeholder.id < 0
+                    WHERE resolved.id IN (
+                        SELECT {foreign_key} FROM {junction}
+                        WHERE game_id IN ({game_ids}) AND {foreign_key} >= 0
+                    )
+                ),
+                relinked AS (
+                    INSERT INTO {junction} (game_id, {foreign_key})
+                    SELECT link.game_id, resolutions.resolved_id
+                    FROM {junction} AS link
+                    JOIN resolutions ON resolutions.placeholder_id = link.{foreign_key}
+                    ON CONFLICT DO NOTHING
+                )
+                DELETE FROM {table}
+                WHERE id IN (SELECT placeholder_id FROM resolutions)
+                "#,
+                table = reference.table,
+                junction = reference.junction,
+                foreign_key = reference.foreign_key,
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Assemble the DTOs of `games`, loading their platforms, genres, themes and game modes
+    pub async fn load_dtos<C>(db: &C, games: Vec<Model>) -> Result<Vec<GameDTO>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let platforms = games
+            .load_many_to_many(platforms::Entity, game_platforms::Entity, db)
+            .await?;
+        let genres = games
+            .load_many_to_many(genres::Entity, game_genres::Entity, db)
+            .await?;
+        let themes = games
+            .load_many_to_many(themes::Entity, game_themes::Entity, db)
+            .await?;
+        let game_modes = games
+            .load_many_to_many(game_modes::Entity, game_game_modes::Entity, db)
+            .await?;
+
+        Ok(izip!(games, platforms, genres, themes, game_modes)
+            .map(|(game, platforms, genres, themes, game_modes)| GameDTO {
+                id: game.id,
+                name: game.name,
+                summary: game.summary,
+                aggregated_rating: game.aggregated_rating,
+                themes: names(themes.into_iter().map(|theme| theme.name)),
+                igdb_url: game.igdb_url,
+                first_release_date: game.first_release_date,
+                franchise: game.franchise,
+                genres: names(genres.into_iter().map(|genre| genre.name)),
+                game_modes: names(game_modes.into_iter().map(|game_mode| game_mode.name)),
+                supports_online_multiplayer: game.supports_online_multiplayer,
+                platforms: names(platforms.into_iter().map(|platform| platform.name)),
+                artwork_url: game.artwork_url,
+                cover_art_url: game.cover_art_url,
+            })
+            .collect())
     }
 
     pub async fn create<C>(db: &C, json: IgdbGame) -> Result<Model, DbErr>
     where
         C: ConnectionTrait,
     {
-        let model = ActiveModel {
+        let model = Self::active_model(json).insert(db).await?;
+        Ok(model)
+    }
+
+    fn active_model(json: IgdbGame) -> ActiveModel {
+        ActiveModel {
             id: Set(json.id),
             name: Set(json.name.clone()),
             searchable_name: Set(Self::make_searchable_name(json.name)),
             summary: Set(json.summary),
             aggregated_rating: Set(json.aggregated_rating),
-            themes: Set(json.themes.map(|themes| themes.join(","))),
+            total_rating_count: Set(json.total_rating_count),
+            hypes: Set(json.hypes),
             igdb_url: Set(json.url),
             first_release_date: Set(json.first_release_date),
             franchise: Set(json.franchise),
-            genres: Set(json.genres.map(|genres| genres.join(","))),
-            game_modes: Set(json.game_modes.map(|game_modes| game_modes.join(","))),
             supports_online_multiplayer: Set(json.supports_online_multiplayer),
-            platforms: Set(json.platforms.map(|platforms| platforms.join(","))),
             // FIXME(Dan): Make not thumbnail
             cover_art_url: Set(json.cover),
             artwork_url: Set(json.artworks),
-        };
-        let model = model.insert(db).await?;
-        Ok(model)
+        }
     }
 
     pub fn make_searchable_name(name: String) -> String {
-        name.chars()
-            .filter_map(|char| {
-                if Self::UNSEARCHABLE_CHARS.contains(&char) {
-                    None
-                } else {
-                    Some(char.to_ascii_lowercase())
-                }
-            })
-            .collect::<String>()
-    }
-}
-
-impl Model {
-    pub fn to_json(self) -> GameDTO {
-        GameDTO {
-            id: self.id,
-            name: self.name,
-            summary: self.summary,
-            aggregated_rating: self.aggregated_rating,
-            themes: self
-                .themes
-                .map(|themes| themes.split(",").map(String::from).collect()),
-            igdb_url: self.igdb_url,
-            first_release_date: self.first_release_date,
-            franchise: self.franchise,
-            genres: self
-                .genres
-                .map(|themes| themes.split(",").map(String::from).collect()),
-            game_modes: self
-                .game_modes
-                .map(|game_mode| game_mode.split(",").map(String::from).collect()),
-            supports_online_multiplayer: self.supports_online_multiplayer,
-            platforms: self
-                .platforms
-                .map(|platform| platform.split(",").map(String::from).collect()),
-            artwork_url: self.artwork_url,
-            cover_art_url: self.cover_art_url,
+        normalise::searchable_name(&name)
+    }
+}
+
+/// Sorted names, or `None` if there are none, as IGDB omits empty lists
+fn names(names: impl Iterator<Item = String>) -> Option<Vec<String>> {
+    let names: Vec<String> = names.sorted().collect();
+    (!names.is_empty()).then_some(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::QueryTrait;
+
+    use super::*;
+
+    fn filter() -> GameFilter {
+        GameFilter {
+            platform: Some("PC".to_string()),
+            genre: Some("Shooter".to_string()),
+            released_after: NaiveDate::from_ymd_opt(2004, 1, 1),
+            released_before: NaiveDate::from_ymd_opt(2005, 1, 1),
+            min_rating: Some(80.5),
+            online_multiplayer: Some(true),
+            ..GameFilter::default()
         }
     }
+
+    #[test]
+    fn validates_filters() {
+        assert!(GameFilter::default().validate().is_ok());
+        assert!(filter().validate().is_ok());
+
+        for min_rating in [-1.0, 100.5, f32::NAN, f32::INFINITY] {
+            let filter = GameFilter {
+                min_rating: Some(min_rating),
+                ..filter()
+            };
+            assert!(filter.validate().is_err(), "min_rating {min_rating}");
+        }
+
+        let filter = GameFilter {
+            released_after: filter().released_before,
+            ..filter()
+        };
+        assert!(filter.validate().is_err());
+    }
+
+    #[test]
+    fn renders_igdb_filters() {
+        assert_eq!(GameFilter::default().igdb_filter(), None);
+        assert_eq!(
+            filter().igdb_filter().unwrap().to_string(),
+            "platforms.name ~ \"PC\" & genres.name ~ \"Shooter\" \
+             & first_release_date >= 1072915200 & first_release_date < 1104537600 \
+             & aggregated_rating >= 80.5 & multiplayer_modes.onlinecoop = true"
+        );
+
+        // IGDB can't be asked for games without online multiplayer
+        let filter = GameFilter {
+            online_multiplayer: Some(false),
+            ..GameFilter::default()
+        };
+        assert_eq!(filter.igdb_filter(), None);
+    }
+
+    #[test]
+    fn keys_searches_by_query_and_filter() {
+        assert_eq!(GameFilter::default().key("halo"), "halo");
+        assert_eq!(
+            filter().key("halo"),
+            "halo?platform=pc&genre=shooter&released_after=2004-01-01\
+             &released_before=2005-01-01&min_rating=80.5&online_multiplayer=true"
+        );
+
+        // Names are matched ignoring case, so the same search either way
+        let shouting = GameFilter {
+            platform: Some("pc".to_string()),
+            genre: Some("SHOOTER".to_string()),
+            ..filter()
+        };
+        assert_eq!(shouting.key("halo"), filter().key("halo"));
+    }
+
+    #[test]
+    fn keys_igdb_searches_by_normalised_query() {
+        assert_eq!(
+            GameFilter::default().search_key("Halo: Reach"),
+            GameFilter::default().search_key("halo reach")
+        );
+        assert_eq!(
+            filter().search_key("Halo"),
+            filter().key(&Entity::make_searchable_name("halo".to_string()))
+        );
+    }
+
+    #[test]
+    fn builds_conditions() {
+        let sql = |filter: &GameFilter| {
+            Entity::find()
+                .filter(filter.condition())
+                .build(DbBackend::Postgres)
+                .to_string()
+        };
+
+        assert!(GameFilter::default().condition().is_empty());
+
+        let filtered = sql(&filter());
+        for expected in [
+            "lower(reference.name) = lower('PC')",
+            "lower(reference.name) = lower('Shooter')",
+            r#""first_release_date" >= '2004-01-01 00:00:00'"#,
+            r#""first_release_date" < '2005-01-01 00:00:00'"#,
+            r#""aggregated_rating" >= 80.5"#,
+            r#""supports_online_multiplayer" = TRUE"#,
+        ] {
+            assert!(filtered.contains(expected), "{expected} in {filtered}");
+        }
+
+        let without_online_multiplayer = GameFilter {
+            online_multiplayer: Some(false),
+            ..GameFilter::default()
+        };
+        assert!(sql(&without_online_multiplayer).contains(
+            r#"WHERE "games"."supports_online_multiplayer" = FALSE OR "games"."supports_online_multiplayer" IS NULL"#
+        ));
+    }
 }
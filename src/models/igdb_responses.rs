@@ -0,0 +1,47 @@
+use chrono::{Duration, Utc};
+use sea_orm::{prelude::*, sea_query::OnConflict, Set};
+use sha2::{Digest, Sha256};
+
+use super::_entities::igdb_responses::{ActiveModel, Column, Entity, Model};
+
+impl Entity {
+    /// The key a response is cached under. `query` should already be canonicalised, so that
+    /// equivalent queries share a cache entry
+    pub fn hash_query(endpoint: &str, query: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(endpoint);
+        hasher.update("\n");
+        hasher.update(query);
+        hex::encode(hasher.finalize())
+    }
+
+    pub async fn upsert<C: ConnectionTrait>(
+        db: &C,
+        endpoint: String,
+        query: String,
+        response: Json,
+    ) -> Result<Model, DbErr> {
+        let active_model = ActiveModel {
+            query_hash: Set(Self::hash_query(&endpoint, &query)),
+            endpoint: Set(endpoint),
+            query: Set(query),
+            response: Set(response),
+            fetched_at: Set(Utc::now().naive_utc()),
+        };
+
+        Self::insert(active_model)
+            .on_conflict(
+                OnConflict::column(Column::QueryHash)
+                    .update_columns([Column::Response, Column::FetchedAt])
+                    .to_owned(),
+            )
+            .exec_with_returning(db)
+            .await
+    }
+}
+
+impl Model {
+    pub fn fetched_within(&self, ttl: Duration) -> bool {
+        (Utc::now().naive_utc() - self.fetched_at) < ttl
+    }
+}
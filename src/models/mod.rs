@@ -1,4 +1,7 @@
 pub mod _entities;
 
+pub mod game_aliases;
 pub mod games;
+pub mod igdb_responses;
 pub mod queries;
+pub mod rate_limits;
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use sea_orm::{prelude::*, ConnectionTrait, DbBackend, Statement};
+
+use super::_entities::rate_limits::Entity;
+
+impl Entity {
+    /// Reserve one request from a rate limit shared by every igdbc instance using the database,
+    /// returning how long to wait before making the request.
+    ///
+    /// Implemented as a generic cell rate algorithm: each reservation pushes the limit's
+    /// theoretical arrival time back by one emission interval, and a request may go ahead once it
+    /// is no more than `burst` intervals ahead of the database's clock. The row lock taken by the
+    /// `UPDATE` serialises reservations across instances.
+    pub async fn reserve<C>(
+        db: &C,
+        name: &str,
+        emission_interval: Duration,
+        burst: u32,
+    ) -> Result<Duration, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let emission_interval = emission_interval.as_secs_f64();
+        let tolerance = emission_interval * f64::from(burst.max(1));
+
+        let row = db
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+                UPDATE rate_limits
+                SET theoretical_arrival =
+                    GREATEST(theoretical_arrival, clock_timestamp()) + make_interval(secs => $1)
+                WHERE name = $2
+                RETURNING EXTRACT(EPOCH FROM (
+                    theoretical_arrival - make_interval(secs => $3) - clock_timestamp()
+                ))::float8 AS wait_seconds
+                "#,
+                [emission_interval.into(), name.into(), tolerance.into()],
+            ))
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("rate limit '{name}'")))?;
+
+        let wait_seconds: f64 = row.try_get("", "wait_seconds")?;
+
+        Ok(Duration::from_secs_f64(wait_seconds.max(0.0)))
+    }
+}
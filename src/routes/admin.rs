@@ -0,0 +1,186 @@
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use reqwest::StatusCode;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, Set, SqlErr,
+};
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use views::GameAliasDTO;
+
+use super::auth::require_api_key;
+use super::games::GameFetchError;
+use crate::error::IgdbcError;
+use crate::models::_entities::sea_orm_active_enums::AliasSource;
+use crate::models::_entities::{game_aliases, games};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/aliases", get(list_aliases).post(create_alias))
+        .route("/aliases/:id", put(update_alias).delete(delete_alias))
+}
+
+#[derive(Error, Debug, Clone)]
+#[repr(u8)]
+pub enum AliasError {
+    #[error("The alias you provided has no letters or digits, so would match every game")]
+    NoLettersOrDigits = 0,
+
+    #[error("The game already has a curated alias differing only in case or punctuation")]
+    Duplicate = 1,
+}
+
+impl AliasError {
+    pub fn code(&self) -> u8 {
+        match self {
+            AliasError::NoLettersOrDigits => 0,
+            AliasError::Duplicate => 1,
+        }
+    }
+}
+
+impl IntoResponse for AliasError {
+    fn into_response(self) -> Response {
+        let status_code = match &self {
+            AliasError::Duplicate => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        let json = Json(json!({
+            "message": self.to_string(),
+            "code": self.code()
+        }));
+
+        (status_code, json).into_response()
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AliasQueryParams {
+    game_id: Option<i32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct NewAlias {
+    game_id: i32,
+    alias: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AliasUpdate {
+    alias: String,
+}
+
+/// List the curated aliases, optionally only those of one game
+async fn list_aliases(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<AliasQueryParams>,
+) -> Result<Json<Vec<GameAliasDTO>>, IgdbcError> {
+    require_api_key(&headers, &state.admin.api_keys)?;
+
+    let mut query = game_aliases::Entity::find()
+        .filter(game_aliases::Column::Source.eq(AliasSource::Curated))
+        .order_by_asc(game_aliases::Column::Id);
+    if let Some(game_id) = params.game_id {
+        query = query.filter(game_aliases::Column::GameId.eq(game_id));
+    }
+
+    let aliases = query.all(&state.db).await?;
+
+    Ok(Json(
+        aliases.into_iter().map(|alias| alias.to_json()).collect(),
+    ))
+}
+
+async fn create_alias(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(new_alias): Json<NewAlias>,
+) -> Result<(StatusCode, Json<GameAliasDTO>), IgdbcError> {
+    require_api_key(&headers, &state.admin.api_keys)?;
+
+    games::Entity::find_by_id(new_alias.game_id)
+        .one(&state.db)
+        .await?
+        .ok_or(GameFetchError::IdNotFound(new_alias.game_id))?;
+
+    validate_alias(&new_alias.alias)?;
+    let alias = game_aliases::Entity::create_curated(&state.db, new_alias.game_id, new_alias.alias)
+        .await
+        .map_err(conflict_on_duplicate)?;
+
+    Ok((StatusCode::CREATED, Json(alias.to_json())))
+}
+
+async fn update_alias(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(update): Json<AliasUpdate>,
+) -> Result<Json<GameAliasDTO>, IgdbcError> {
+    require_api_key(&headers, &state.admin.api_keys)?;
+
+    let alias = find_curated(&state, id).await?;
+
+    let searchable_alias = validate_alias(&update.alias)?;
+    let mut active_model: game_aliases::ActiveModel = alias.into();
+    active_model.alias = Set(update.alias);
+    active_model.searchable_alias = Set(searchable_alias);
+    let alias = active_model
+        .update(&state.db)
+        .await
+        .map_err(conflict_on_duplicate)?;
+
+    Ok(Json(alias.to_json()))
+}
+
+async fn delete_alias(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, IgdbcError> {
+    require_api_key(&headers, &state.admin.api_keys)?;
+
+    let alias = find_curated(&state, id).await?;
+    game_aliases::Entity::delete_by_id(alias.id)
+        .exec(&state.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Aliases from IGDB are replaced whenever their game is refreshed, so only curated ones can be
+/// managed
+async fn find_curated(state: &AppState, id: i32) -> Result<game_aliases::Model, IgdbcError> {
+    game_aliases::Entity::find_by_id(id)
+        .filter(game_aliases::Column::Source.eq(AliasSource::Curated))
+        .one(&state.db)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND.into())
+}
+
+/// The searchable form of an alias, rejecting aliases that would match every game
+fn validate_alias(alias: &str) -> Result<String, IgdbcError> {
+    let searchable_alias = games::Entity::make_searchable_name(alias.to_string());
+
+    if searchable_alias.is_empty() {
+        return Err(AliasError::NoLettersOrDigits.into());
+    }
+
+    Ok(searchable_alias)
+}
+
+/// A game's curated aliases must normalise differently. The unique index enforces this, so that
+/// concurrent requests adding the same alias can't both succeed
+fn conflict_on_duplicate(error: DbErr) -> IgdbcError {
+    match error.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => AliasError::Duplicate.into(),
+        _ => error.into(),
+    }
+}
@@ -0,0 +1,28 @@
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::error::IgdbcError;
+
+/// Check that the request carries one of `api_keys` as a bearer token
+pub fn require_api_key(headers: &HeaderMap, api_keys: &[String]) -> Result<(), IgdbcError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) if api_keys.iter().any(|key| keys_match(key, token)) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED.into()),
+    }
+}
+
+/// Compare without short-circuiting, so response times don't reveal how much of a key matched
+fn keys_match(key: &str, token: &str) -> bool {
+    key.len() == token.len()
+        && key
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
@@ -2,21 +2,30 @@ use axum::extract::{Path, Query, State};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
 use reqwest::StatusCode;
 use sea_orm::EntityTrait;
 use serde::Deserialize;
 use serde_json::json;
+use sha2::Sha256;
 use thiserror::Error;
 use tracing::info;
-use views::GameDTO;
+use views::{GameDTO, GamePage};
 
 use crate::error::IgdbcError;
-use crate::models::_entities::games::{self, Entity};
+use crate::igdb::IgdbStatus;
+use crate::models::_entities::games;
 use crate::models::_entities::queries;
-use crate::{search_igdb, AppState};
+use crate::models::games::{GameFilter, GameSort, SearchPosition};
+use crate::{search_igdb_deduplicated, AppState};
 
 const MAX_GAME_QUERY_LENGTH: usize = 32;
-const MAX_RESULTS: usize = 10;
+const DEFAULT_PAGE_SIZE: usize = 10;
+/// IGDB is searched when the cache holds fewer games than this for a search, whatever the page
+/// size asked for
+const REFRESH_THRESHOLD: usize = 10;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -35,6 +44,12 @@ pub enum GameFetchError {
 
     #[error("Could not find a game with ID '{0}'")]
     IdNotFound(i32) = 2,
+
+    #[error("The cursor you provided is invalid or belongs to a different search")]
+    InvalidCursor = 3,
+
+    #[error("The filter you provided is invalid: {0}")]
+    InvalidFilter(&'static str) = 4,
 }
 
 impl GameFetchError {
@@ -43,6 +58,8 @@ impl GameFetchError {
             GameFetchError::RepopulatingCache => 0,
             GameFetchError::QueryTooLong => 1,
             GameFetchError::IdNotFound(_) => 2,
+            GameFetchError::InvalidCursor => 3,
+            GameFetchError::InvalidFilter(_) => 4,
         }
     }
 }
@@ -66,50 +83,168 @@ impl IntoResponse for GameFetchError {
 #[derive(Clone, Deserialize)]
 pub struct GameQueryParams {
     query: String,
+    #[serde(default)]
+    sort: GameSort,
+    /// How many games to return, up to the configured maximum page size
+    limit: Option<usize>,
+    /// Where to continue from, as returned by the previous page
+    cursor: Option<String>,
 }
 
 async fn query_games(
     State(state): State<AppState>,
     Query(params): Query<GameQueryParams>,
-) -> Result<Json<Vec<GameDTO>>, IgdbcError> {
+    Query(filter): Query<GameFilter>,
+) -> Result<Json<GamePage>, IgdbcError> {
     let query = params.query;
 
     // game name length for 2018 ranged up to around 28. Add a bit of padding by doubling
     if query.len() > MAX_GAME_QUERY_LENGTH {
         return Err(GameFetchError::QueryTooLong.into());
     }
+    filter.validate().map_err(GameFetchError::InvalidFilter)?;
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, state.search.max_page_size.max(1));
+    // Identifies the search, so that its cursors can't be used to continue another
+    let search = format!("{}\n{}", params.sort.as_str(), filter.key(&query));
+    let after = match params.cursor {
+        Some(token) => Some(
+            state
+                .cursors
+                .verify(&token, &search)
+                .ok_or(GameFetchError::InvalidCursor)?,
+        ),
+        None => None,
+    };
+
+    // One more than the page holds, to tell whether there is a next page. The first page looks
+    // far enough ahead to tell whether the cache holds enough games to go without a refresh
+    let fetch = match after {
+        Some(_) => limit + 1,
+        None => (limit + 1).max(REFRESH_THRESHOLD),
+    };
 
     info!("Querying internal database for {query}");
-    let games: Vec<GameDTO> = games::Entity::find_by_query(&state.db, query.clone(), MAX_RESULTS)
-        .await?
-        .into_iter()
-        .map(|game| game.to_json())
-        .collect();
+    let find_games = || {
+        games::Entity::search(
+            &state.db,
+            query.clone(),
+            fetch,
+            after.as_ref(),
+            params.sort,
+            &filter,
+            &state.search,
+        )
+    };
+    let mut games = find_games().await?;
 
-    if games.len() >= MAX_RESULTS {
-        return Ok(Json(games));
+    // Only the first page refreshes the cache, so that later pages continue through the same
+    // results
+    if after.is_none()
+        && games.len() < REFRESH_THRESHOLD
+        && should_refresh(&state, &query, &filter).await?
+    {
+        search_igdb_deduplicated(&state, query.clone(), filter.clone()).await?;
+        games = find_games().await?;
     }
 
-    let maybe_query = queries::Entity::find_by_id(query.to_string())
+    let next_cursor = (games.len() > limit).then(|| {
+        state
+            .cursors
+            .sign(&games[limit - 1].position(params.sort), &search)
+    });
+    games.truncate(limit);
+
+    let games = games.into_iter().map(|ranked| ranked.game).collect();
+
+    Ok(Json(GamePage {
+        games: games::Entity::load_dtos(&state.db, games).await?,
+        next_cursor,
+    }))
+}
+
+/// Whether IGDB should be searched for more games matching `query` and `filter`
+async fn should_refresh(
+    state: &AppState,
+    query: &str,
+    filter: &GameFilter,
+) -> Result<bool, IgdbcError> {
+    if state.igdb.status() == IgdbStatus::Offline {
+        info!("IGDB is offline - serving from cache only.");
+        return Ok(false);
+    }
+
+    let maybe_query = queries::Entity::find_by_id(filter.search_key(query))
         .one(&state.db)
         .await?;
 
     if let Some(ref query_model) = maybe_query {
         if query_model.queried_recently() {
             info!("Not requerying - already queried recently.");
-            return Ok(Json(games));
+            return Ok(false);
         }
     }
 
-    let games = search_igdb(&state.db, query.clone()).await?;
+    Ok(true)
+}
 
-    Ok(Json(
-        games
-            .into_iter()
-            .take(MAX_RESULTS)
-            .map(|game| game.to_json())
-            .collect(),
-    ))
+/// Hands clients the position a page of search results ends at as an opaque cursor, signed so
+/// that clients can neither forge positions nor continue one search with another's cursor
+pub struct CursorSigner {
+    key: Vec<u8>,
+}
+
+impl CursorSigner {
+    /// Sign with `secret`, or a random key if `None`
+    pub fn new(secret: Option<&str>) -> Self {
+        let key = match secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => rand::random::<[u8; 32]>().to_vec(),
+        };
+        Self { key }
+    }
+
+    /// A cursor continuing `search` after `position`
+    fn sign(&self, position: &SearchPosition, search: &str) -> String {
+        let payload = serde_json::to_string(position).expect("positions are serialisable");
+        let signature = self.mac(&payload, search).finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// The position a cursor continues `search` after, if it was signed for that search
+    fn verify(&self, cursor: &str, search: &str) -> Option<SearchPosition> {
+        let (payload, signature) = cursor.split_once('.')?;
+        let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        self.mac(&payload, search).verify_slice(&signature).ok()?;
+
+        serde_json::from_str(&payload).ok()
+    }
+
+    /// Serialised positions never contain a newline, so can't run into the search
+    fn mac(&self, payload: &str, search: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any size");
+        mac.update(payload.as_bytes());
+        mac.update(b"\n");
+        mac.update(search.as_bytes());
+        mac
+    }
+}
+
+impl Default for CursorSigner {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
 
 async fn get_game(
@@ -121,5 +256,90 @@ async fn get_game(
         .await?
         .ok_or(GameFetchError::IdNotFound(id))?;
 
-    Ok(Json(game.to_json()))
+    let mut games = games::Entity::load_dtos(&state.db, vec![game]).await?;
+
+    Ok(Json(games.remove(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SEARCH: &str = "rating\nhalo?platform=pc";
+
+    fn position_at(rating: f32, relevance: f64) -> SearchPosition {
+        serde_json::from_value(json!({
+            "sort_key": { "rating": rating.to_bits() },
+            "relevance": relevance.to_bits(),
+            "id": 7,
+        }))
+        .unwrap()
+    }
+
+    fn position() -> SearchPosition {
+        position_at(85.5, 4.123_456_789_012_345)
+    }
+
+    #[test]
+    fn round_trips_positions() {
+        let signer = CursorSigner::default();
+
+        // Includes values that don't survive being written as decimals and parsed back, such as
+        // relevances near 9.128683752071419
+        let relevances = (0..10_000).map(|step| 9.128_683_752_071_419 + step as f64 * 1e-13);
+        let ratings = (0..10_000).map(|step| step as f32 / 99.0);
+        for (rating, relevance) in ratings.zip(relevances) {
+            let position = position_at(rating, relevance);
+            let cursor = signer.sign(&position, SEARCH);
+            assert_eq!(signer.verify(&cursor, SEARCH), Some(position));
+        }
+    }
+
+    #[test]
+    fn rejects_cursors_of_other_searches_or_keys() {
+        let signer = CursorSigner::new(Some("a secret"));
+        let cursor = signer.sign(&position(), SEARCH);
+
+        assert_eq!(signer.verify(&cursor, "name\nhalo?platform=pc"), None);
+        assert_eq!(signer.verify(&cursor, "rating\nhalo"), None);
+        assert_eq!(
+            CursorSigner::new(Some("another secret")).verify(&cursor, SEARCH),
+            None
+        );
+        assert_eq!(
+            CursorSigner::new(Some("a secret")).verify(&cursor, SEARCH),
+            Some(position())
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_cursors() {
+        let signer = CursorSigner::default();
+        let cursor = signer.sign(&position(), SEARCH);
+        let (_, signature) = cursor.split_once('.').unwrap();
+
+        let forged_payload = URL_SAFE_NO_PAD.encode(
+            json!({ "sort_key": { "rating": 85.5f32.to_bits() }, "relevance": 4.0f64.to_bits(), "id": 7 })
+                .to_string(),
+        );
+        let forged = format!("{forged_payload}.{signature}");
+        assert_eq!(signer.verify(&forged, SEARCH), None);
+
+        for garbage in [
+            "",
+            ".",
+            "MTA",
+            "MTA.",
+            &format!("{cursor}A"),
+            "not base64!.x",
+        ] {
+            assert_eq!(
+                signer.verify(garbage, SEARCH),
+                None,
+                "verifying {garbage:?}"
+            );
+        }
+    }
 }
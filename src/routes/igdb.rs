@@ -0,0 +1,92 @@
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::Duration;
+use sea_orm::EntityTrait;
+use serde_json::Value;
+use tracing::info;
+
+use super::auth::require_api_key;
+use crate::configuration;
+use crate::error::IgdbcError;
+use crate::igdb::apicalypse::policy::QueryPolicy;
+use crate::igdb::apicalypse::ApicalypseQuery;
+use crate::igdb::IgdbStatus;
+use crate::models::_entities::igdb_responses;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/:endpoint", post(query_endpoint))
+}
+
+/// Who may use the raw Apicalypse endpoint, what they may query, and for how long responses are
+/// cached. The default rejects every request
+#[derive(Default)]
+pub struct Passthrough {
+    api_keys: Vec<String>,
+    policy: QueryPolicy,
+    cache_ttl: Duration,
+}
+
+impl From<&configuration::Passthrough> for Passthrough {
+    fn from(config: &configuration::Passthrough) -> Self {
+        let policy = config
+            .endpoints
+            .iter()
+            .fold(QueryPolicy::new(), |policy, endpoint| {
+                match config.fields.get(endpoint) {
+                    Some(fields) => policy.allow_fields(endpoint, fields.clone()),
+                    None => policy.allow_endpoint(endpoint),
+                }
+            });
+
+        Self {
+            api_keys: config.api_keys.clone(),
+            policy,
+            // Out of range TTLs are rejected when the configuration is loaded
+            cache_ttl: cache_ttl(config.cache_ttl_secs).unwrap_or_else(Duration::max_value),
+        }
+    }
+}
+
+/// The TTL of cached responses, or `None` if it is too long to represent
+pub fn cache_ttl(secs: u64) -> Option<Duration> {
+    i64::try_from(secs).ok().and_then(Duration::try_seconds)
+}
+
+/// Forward an Apicalypse query to IGDB, serving the response from the database if the same
+/// query was made recently
+async fn query_endpoint(
+    State(state): State<AppState>,
+    Path(endpoint): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<Value>, IgdbcError> {
+    let passthrough = &state.passthrough;
+    require_api_key(&headers, &passthrough.api_keys)?;
+
+    let query = ApicalypseQuery::parse(&body)?.canonicalize();
+    passthrough.policy.check(&endpoint, &query)?;
+
+    let rendered_query = query.to_string();
+    let query_hash = igdb_responses::Entity::hash_query(&endpoint, &rendered_query);
+
+    if let Some(cached) = igdb_responses::Entity::find_by_id(query_hash)
+        .one(&state.db)
+        .await?
+    {
+        // A stale response beats none while IGDB is unreachable
+        if cached.fetched_within(passthrough.cache_ttl)
+            || state.igdb.status() == IgdbStatus::Offline
+        {
+            return Ok(Json(cached.response));
+        }
+    }
+
+    info!("Forwarding query against '{endpoint}' to IGDB");
+    let response = state.igdb.raw_query(&endpoint, query).await?;
+    igdb_responses::Entity::upsert(&state.db, endpoint, rendered_query, response.clone()).await?;
+
+    Ok(Json(response))
+}
@@ -1,27 +1,41 @@
+use std::sync::Arc;
+
 use axum::Router;
-use sea_orm::Database;
+use sea_orm::DatabaseConnection;
 use tower_http::trace::{self, TraceLayer};
 use tracing::Level;
 
-use crate::{db::init_database, error::IgdbcError, AppState};
+use crate::configuration::Config;
+use crate::{igdb::IgdbApi, AppState};
 
+pub mod admin;
+mod auth;
 pub mod games;
+pub mod igdb;
+pub mod status;
 
-pub async fn app(db_url: &str) -> Result<Router, IgdbcError> {
-    let db = Database::connect(db_url).await?;
-    init_database(&db).await?;
-
-    let state = AppState { db };
+pub fn app(db: DatabaseConnection, igdb: Arc<dyn IgdbApi>, config: &Config) -> Router {
+    router(
+        AppState::new(db, igdb)
+            .with_search(config.search.clone())
+            .with_passthrough((&config.passthrough).into())
+            .with_admin(config.admin.clone()),
+    )
+}
 
-    let router = Router::new()
+/// Build the application router around an existing state, e.g. one backed by
+/// [`crate::igdb::fake::FakeIgdb`]
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .nest("/admin", admin::router())
         .nest("/games", games::router())
+        .nest("/igdb", igdb::router())
+        .nest("/status", status::router())
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
                 .on_response(trace::DefaultOnResponse::new().level(Level::INFO))
                 .on_failure(trace::DefaultOnFailure::new().level(Level::INFO)),
         )
-        .with_state(state);
-
-    Ok(router)
+        .with_state(state)
 }
@@ -0,0 +1,21 @@
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use crate::igdb::IgdbStatus;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(get_status))
+}
+
+/// Report whether igdbc is fully operational or degraded to serving cached games only
+async fn get_status(State(state): State<AppState>) -> Json<Value> {
+    let igdb = state.igdb.status();
+
+    Json(json!({
+        "igdb": igdb,
+        "cache_only": igdb == IgdbStatus::Offline,
+    }))
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+
+/// Deduplicates concurrent work by key: while a future for a key is in flight, further callers
+/// with the same key wait on it instead of starting their own, and all of them receive its output
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, Shared<BoxFuture<'static, V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::default(),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Run the future produced by `work`, unless one is already in flight for `key`, in which case
+    /// wait for that one instead
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        let future = self
+            .in_flight
+            .lock()
+            .expect("single-flight mutex poisoned")
+            .entry(key.clone())
+            .or_insert_with(|| work().boxed().shared())
+            .clone();
+
+        let output = future.clone().await;
+
+        // Whichever waiter finishes first clears the entry, so later callers start afresh. The
+        // pointer check avoids removing a newer flight that started after this one finished
+        let mut in_flight = self.in_flight.lock().expect("single-flight mutex poisoned");
+        if in_flight
+            .get(&key)
+            .is_some_and(|current| current.ptr_eq(&future))
+        {
+            in_flight.remove(&key);
+        }
+
+        output
+    }
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use common::{igdb_game, send, test_db};
+use futures::future::join_all;
+use igdbc::configuration::Admin;
+use igdbc::igdb::fake::FakeIgdb;
+use igdbc::models::_entities::games;
+use igdbc::AppState;
+use sea_orm::DatabaseConnection;
+use serde_json::{json, Value};
+
+const API_KEY: &str = "secret";
+
+async fn app(db: &DatabaseConnection) -> Router {
+    for (id, name) in [(1, "Portal"), (2, "Half-Life")] {
+        games::Entity::create(db, igdb_game(id, name, json!({})))
+            .await
+            .unwrap();
+    }
+
+    igdbc::routes::router(
+        AppState::new(db.clone(), Arc::new(FakeIgdb::default())).with_admin(Admin {
+            api_keys: vec![API_KEY.to_string()],
+        }),
+    )
+}
+
+fn request(method: Method, uri: &str, body: Option<Value>) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Authorization", format!("Bearer {API_KEY}"))
+        .header("Content-Type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap()
+}
+
+fn create(game_id: i32, alias: &str) -> Request<Body> {
+    request(
+        Method::POST,
+        "/admin/aliases",
+        Some(json!({ "game_id": game_id, "alias": alias })),
+    )
+}
+
+fn update(id: &Value, alias: &str) -> Request<Body> {
+    request(
+        Method::PUT,
+        &format!("/admin/aliases/{id}"),
+        Some(json!({ "alias": alias })),
+    )
+}
+
+#[tokio::test]
+async fn requires_an_api_key() {
+    let test_db = test_db!();
+    let app = app(&test_db.db).await;
+
+    let (status, _) = send(&app, common::get("/admin/aliases")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn manages_curated_aliases() {
+    let test_db = test_db!();
+    let app = app(&test_db.db).await;
+
+    let (status, created) = send(&app, create(1, "Aperture Science")).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["alias"], "Aperture Science");
+    let id = &created["id"];
+
+    let (status, updated) = send(&app, update(id, "Aperture")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["alias"], "Aperture");
+
+    let (status, aliases) =
+        send(&app, request(Method::GET, "/admin/aliases?game_id=1", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(aliases, json!([updated]));
+
+    let uri = format!("/admin/aliases/{id}");
+    let (status, _) = send(&app, request(Method::DELETE, &uri, None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, request(Method::DELETE, &uri, None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_invalid_aliases() {
+    let test_db = test_db!();
+    let app = app(&test_db.db).await;
+
+    let (status, body) = send(&app, create(1, "!!!")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], 0);
+    assert!(body["message"]
+        .as_str()
+        .is_some_and(|message| message.contains("no letters or digits")));
+
+    let (status, _) = send(&app, create(3, "Black Mesa")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_aliases_that_normalise_like_another_of_the_game() {
+    let test_db = test_db!();
+    let app = app(&test_db.db).await;
+
+    let (status, _) = send(&app, create(1, "Aperture")).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, body) = send(&app, create(1, "APERTURE!")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], 1);
+
+    // Other games may share the alias
+    let (status, _) = send(&app, create(2, "Aperture")).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, other) = send(&app, create(1, "Chell")).await;
+    let (status, _) = send(&app, update(&other["id"], "aperture")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn only_one_of_concurrent_duplicates_is_created() {
+    let test_db = test_db!();
+    let app = app(&test_db.db).await;
+
+    let responses = join_all((0..8).map(|_| send(&app, create(1, "Aperture")))).await;
+    let mut statuses: Vec<_> = responses.into_iter().map(|(status, _)| status).collect();
+    statuses.sort();
+
+    let mut expected = vec![StatusCode::CONFLICT; 7];
+    expected.insert(0, StatusCode::CREATED);
+    assert_eq!(statuses, expected);
+}
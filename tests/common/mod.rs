@@ -0,0 +1,130 @@
+//! Shared setup for tests that run igdbc against Postgres. Each test gets a freshly migrated
+//! database of its own on the server at `IGDBC_TEST_DATABASE_URL` (e.g.
+//! `postgres://postgres@localhost:5432`), whose user must be allowed to create databases. Tests
+//! are skipped when it is unset
+
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use igdbc::igdb::IgdbGame;
+use migration::MigratorTrait;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+use serde_json::Value;
+use tower::ServiceExt;
+
+static DATABASES: AtomicUsize = AtomicUsize::new(0);
+
+/// Connect to a new database for the test, or return from it if there is no server to test with
+macro_rules! test_db {
+    () => {
+        match common::TestDb::create().await {
+            Some(test_db) => test_db,
+            None => return,
+        }
+    };
+}
+pub(crate) use test_db;
+
+/// A migrated database that is dropped along with this
+pub struct TestDb {
+    pub db: DatabaseConnection,
+    server_url: String,
+    name: String,
+}
+
+impl TestDb {
+    pub async fn create() -> Option<Self> {
+        let Ok(server_url) = std::env::var("IGDBC_TEST_DATABASE_URL") else {
+            eprintln!("IGDBC_TEST_DATABASE_URL is unset, skipping");
+            return None;
+        };
+        let server_url = server_url.trim_end_matches('/').to_string();
+        let name = format!(
+            "igdbc_test_{}_{}",
+            std::process::id(),
+            DATABASES.fetch_add(1, Ordering::SeqCst)
+        );
+
+        Database::connect(&server_url)
+            .await
+            .unwrap()
+            .execute_unprepared(&format!("CREATE DATABASE {name}"))
+            .await
+            .unwrap();
+        let db = Database::connect(format!("{server_url}/{name}"))
+            .await
+            .unwrap();
+        migration::Migrator::up(&db, None).await.unwrap();
+
+        Some(Self {
+            db,
+            server_url,
+            name,
+        })
+    }
+
+    /// Another connection to the database, like that of another igdbc instance
+    pub async fn connect(&self) -> DatabaseConnection {
+        Database::connect(format!("{}/{}", self.server_url, self.name))
+            .await
+            .unwrap()
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let server_url = self.server_url.clone();
+        let drop_database = format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name);
+
+        // The test's runtime can't be blocked on from here, so drop the database from one of its
+        // own
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async move {
+                    if let Ok(server) = Database::connect(&server_url).await {
+                        let _ = server.execute_unprepared(&drop_database).await;
+                    }
+                })
+        })
+        .join()
+        .unwrap();
+    }
+}
+
+/// A game as IGDB would return it, with `fields` merged over a minimal game
+pub fn igdb_game(id: i32, name: &str, fields: Value) -> IgdbGame {
+    let mut game = serde_json::json!({
+        "id": id,
+        "name": name,
+        "url": format!("https://www.igdb.com/games/{id}"),
+    });
+    if let (Some(game), Value::Object(fields)) = (game.as_object_mut(), fields) {
+        game.extend(fields);
+    }
+    serde_json::from_value(game).unwrap()
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+/// Send `request` through `app`, returning the response's status and its body as JSON (`Null`
+/// if empty, or a string if not JSON, like axum's rejections)
+pub async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()))
+    };
+    (status, body)
+}
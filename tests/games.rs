@@ -0,0 +1,599 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::Router;
+use common::{get, igdb_game, send, test_db};
+use futures::future::join_all;
+use igdbc::configuration::{FuzzySearch, Search};
+use igdbc::igdb::fake::{FakeIgdb, FakeRequest};
+use igdbc::igdb::{GameSearch, IgdbGame, IgdbStatus};
+use igdbc::models::_entities::games;
+use igdbc::AppState;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde_json::{json, Value};
+
+/// Store `games` as though fetched earlier, and serve searches of them with an IGDB that knows
+/// no games, so that only what is stored is found
+async fn app(db: &DatabaseConnection, games: Vec<IgdbGame>, search: Search) -> Router {
+    for game in games {
+        games::Entity::create_or_update(db, game).await.unwrap();
+    }
+
+    igdbc::routes::router(
+        AppState::new(db.clone(), Arc::new(FakeIgdb::default())).with_search(search),
+    )
+}
+
+/// The names of the games listed in a page of search results
+fn names(page: &Value) -> Vec<&str> {
+    page["games"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|game| game["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn ranks_closer_matches_first() {
+    let test_db = test_db!();
+    let app = app(
+        &test_db.db,
+        vec![
+            igdb_game(1, "Portal Knights", json!({})),
+            igdb_game(2, "Portal 2", json!({ "aggregated_rating": 95.0 })),
+            igdb_game(
+                3,
+                "Aperture Tag",
+                json!({ "summary": "A fan-made portal puzzle game" }),
+            ),
+            igdb_game(4, "Portal", json!({ "aggregated_rating": 90.0 })),
+            igdb_game(5, "Half-Life", json!({})),
+        ],
+        Search::default(),
+    )
+    .await;
+
+    let (status, page) = send(&app, get("/games?query=portal")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        names(&page),
+        ["Portal", "Portal 2", "Portal Knights", "Aperture Tag"]
+    );
+}
+
+#[tokio::test]
+async fn ranks_names_closer_to_misspelt_queries_first() {
+    let test_db = test_db!();
+    let search = Search {
+        fuzzy: FuzzySearch {
+            enabled: true,
+            threshold: 0.2,
+        },
+        ..Search::default()
+    };
+    let app = app(
+        &test_db.db,
+        vec![
+            igdb_game(1, "Portal Knights", json!({})),
+            igdb_game(2, "Portal", json!({})),
+        ],
+        search,
+    )
+    .await;
+
+    let (_, page) = send(&app, get("/games?query=portl")).await;
+    assert_eq!(names(&page), ["Portal", "Portal Knights"]);
+}
+
+#[tokio::test]
+async fn finds_nothing_for_queries_without_letters_or_digits() {
+    let test_db = test_db!();
+    let app = app(
+        &test_db.db,
+        vec![igdb_game(1, "Portal", json!({}))],
+        Search::default(),
+    )
+    .await;
+
+    let (status, page) = send(&app, get("/games?query=!!!")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(names(&page).is_empty());
+}
+
+#[tokio::test]
+async fn sorts_by_the_requested_order() {
+    let test_db = test_db!();
+    let app = app(
+        &test_db.db,
+        vec![
+            igdb_game(
+                1,
+                "Halo 2",
+                json!({ "aggregated_rating": 85.0, "first_release_date": 1_099_958_400 }),
+            ),
+            igdb_game(
+                2,
+                "Halo: Reach",
+                json!({ "first_release_date": 1_284_422_400 }),
+            ),
+            igdb_game(
+                3,
+                "Halo: Combat Evolved",
+                json!({ "aggregated_rating": 97.0, "first_release_date": 1_005_091_200 }),
+            ),
+            igdb_game(4, "Halo Infinite", json!({})),
+        ],
+        Search::default(),
+    )
+    .await;
+
+    let cases = [
+        (
+            "rating",
+            // Unrated games are listed last, by relevance
+            [
+                "Halo: Combat Evolved",
+                "Halo 2",
+                "Halo: Reach",
+                "Halo Infinite",
+            ],
+        ),
+        (
+            "release_date",
+            [
+                "Halo: Reach",
+                "Halo 2",
+                "Halo: Combat Evolved",
+                "Halo Infinite",
+            ],
+        ),
+        (
+            "name",
+            [
+                "Halo 2",
+                "Halo Infinite",
+                "Halo: Combat Evolved",
+                "Halo: Reach",
+            ],
+        ),
+    ];
+
+    for (sort, expected) in cases {
+        let (status, page) = send(&app, get(&format!("/games?query=halo&sort={sort}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&page), expected, "sorting by {sort}");
+    }
+
+    let (status, _) = send(&app, get("/games?query=halo&sort=popularity")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn filters_games() {
+    let test_db = test_db!();
+    let pc = json!([{ "id": 6, "name": "PC (Microsoft Windows)" }]);
+    let app = app(
+        &test_db.db,
+        vec![
+            igdb_game(
+                1,
+                "Halo 2",
+                json!({
+                    "platforms": [{ "id": 11, "name": "Xbox" }],
+                    "aggregated_rating": 85.0,
+                    "first_release_date": 1_099_958_400,
+                    "multiplayer_modes": [{ "onlinecoop": true }],
+                }),
+            ),
+            igdb_game(
+                2,
+                "Halo: Combat Evolved",
+                json!({
+                    "platforms": pc,
+                    "aggregated_rating": 97.0,
+                    "first_release_date": 1_005_091_200,
+                }),
+            ),
+            igdb_game(
+                3,
+                "Halo Infinite",
+                json!({ "platforms": pc, "multiplayer_modes": [{ "onlinecoop": false }] }),
+            ),
+        ],
+        Search::default(),
+    )
+    .await;
+
+    let cases = [
+        (
+            "platform=pc%20(microsoft%20windows)",
+            vec!["Halo Infinite", "Halo: Combat Evolved"],
+        ),
+        ("min_rating=90", vec!["Halo: Combat Evolved"]),
+        (
+            "released_after=2004-01-01&released_before=2005-01-01",
+            vec!["Halo 2"],
+        ),
+        ("online_multiplayer=true", vec!["Halo 2"]),
+        // Including games IGDB has no multiplayer data for
+        (
+            "online_multiplayer=false",
+            vec!["Halo Infinite", "Halo: Combat Evolved"],
+        ),
+    ];
+
+    for (filter, expected) in cases {
+        let (status, page) =
+            send(&app, get(&format!("/games?query=halo&sort=name&{filter}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&page), expected, "filtering by {filter}");
+    }
+}
+
+#[tokio::test]
+async fn rejects_filters_no_game_could_meet() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    for filter in [
+        "min_rating=NaN",
+        "min_rating=inf",
+        "min_rating=-1",
+        "min_rating=101",
+        "released_after=2005-01-01&released_before=2005-01-01",
+    ] {
+        let (status, body) = send(&app, get(&format!("/games?query=halo&{filter}"))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "filtering by {filter}");
+        assert_eq!(body["code"], 4);
+    }
+
+    assert_eq!(igdb.request_count().await, 0);
+}
+
+#[tokio::test]
+async fn passes_filters_on_to_igdb() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::new(vec![igdb_game(
+        1,
+        "Halo 2",
+        json!({ "platforms": [{ "id": 11, "name": "Xbox" }] }),
+    )]));
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    let (_, page) = send(&app, get("/games?query=halo&platform=xbox")).await;
+    assert_eq!(names(&page), ["Halo 2"]);
+
+    let requests = igdb.requests().await;
+    let [FakeRequest::Search(search)] = requests.as_slice() else {
+        panic!("expected a single search, got {requests:?}");
+    };
+    assert_eq!(
+        search.filter.as_ref().map(ToString::to_string).as_deref(),
+        Some(r#"platforms.name ~ "xbox""#)
+    );
+}
+
+/// Every page of a search, followed by cursor
+async fn pages(app: &Router, uri: &str) -> Vec<Value> {
+    let mut pages = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let uri = match &cursor {
+            Some(cursor) => format!("{uri}&cursor={cursor}"),
+            None => uri.to_string(),
+        };
+        let (status, page) = send(app, get(&uri)).await;
+        assert_eq!(status, StatusCode::OK, "requesting {uri}");
+        assert!(pages.len() < 1000, "paging never ends");
+
+        let next = page["next_cursor"].as_str().map(str::to_string);
+        pages.push(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return pages,
+        }
+    }
+}
+
+/// The ids of the games listed across `pages`, in order
+fn ids(pages: &[Value]) -> Vec<i64> {
+    pages
+        .iter()
+        .flat_map(|page| page["games"].as_array().unwrap())
+        .map(|game| game["id"].as_i64().unwrap())
+        .collect()
+}
+
+fn zelda_games() -> Vec<IgdbGame> {
+    [
+        (1, "Zelda II", Some(73.0), Some(567_993_600)),
+        (
+            2,
+            "Zelda: Breath of the Wild",
+            Some(97.0),
+            Some(1_488_499_200),
+        ),
+        (3, "Zelda: Ocarina of Time", Some(97.0), Some(911_606_400)),
+        (4, "Zelda: Skyward Sword", None, Some(1_321_574_400)),
+        (5, "Zelda: Spirit Tracks", None, None),
+        (6, "Zelda: Minish Cap", Some(89.0), None),
+        (7, "Zelda", None, None),
+    ]
+    .into_iter()
+    .map(|(id, name, rating, released)| {
+        igdb_game(
+            id,
+            name,
+            json!({ "aggregated_rating": rating, "first_release_date": released }),
+        )
+    })
+    .collect()
+}
+
+#[tokio::test]
+async fn pages_through_results_without_repeating_or_skipping_games() {
+    let test_db = test_db!();
+    let app = app(&test_db.db, zelda_games(), Search::default()).await;
+
+    for sort in ["relevance", "rating", "release_date", "name"] {
+        let all = pages(&app, &format!("/games?query=zelda&sort={sort}&limit=50")).await;
+        assert_eq!(all.len(), 1);
+        assert_eq!(names(&all[0]).len(), 7);
+
+        let paged = pages(&app, &format!("/games?query=zelda&sort={sort}&limit=2")).await;
+        assert_eq!(
+            paged
+                .iter()
+                .map(|page| names(page).len())
+                .collect::<Vec<_>>(),
+            [2, 2, 2, 1],
+            "paging by {sort}"
+        );
+        assert_eq!(ids(&paged), ids(&all), "paging by {sort}");
+    }
+}
+
+#[tokio::test]
+async fn pages_through_many_ties_and_inexact_relevances() {
+    let test_db = test_db!();
+    // Ratings with many decimal places and logs of counts make relevances that don't survive being
+    // written as decimals, and each game has a twin with the same name and details to tie with
+    let games = (1..=120)
+        .map(|id| {
+            let twin = id % 60;
+            let rating = match twin % 4 {
+                0 => json!(null),
+                _ => json!(40.0 + f64::from(twin) * 0.713_421_9),
+            };
+            igdb_game(
+                id,
+                &format!("Portal {twin}"),
+                json!({
+                    "aggregated_rating": rating,
+                    "total_rating_count": twin * 37 % 23,
+                    "hypes": twin % 13,
+                }),
+            )
+        })
+        .collect();
+    let app = app(&test_db.db, games, Search::default()).await;
+
+    for sort in ["relevance", "rating", "release_date", "name"] {
+        let all = ids(&pages(&app, &format!("/games?query=portal&sort={sort}&limit=50")).await);
+
+        for limit in [1, 7] {
+            let paged = ids(&pages(
+                &app,
+                &format!("/games?query=portal&sort={sort}&limit={limit}"),
+            )
+            .await);
+
+            let mut unique = paged.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(
+                unique,
+                (1..=120).collect::<Vec<_>>(),
+                "paging by {sort} {limit} at a time"
+            );
+            assert_eq!(paged, all, "paging by {sort} {limit} at a time");
+        }
+    }
+}
+
+#[tokio::test]
+async fn continues_from_the_last_game_seen_when_earlier_results_change() {
+    let test_db = test_db!();
+    let app = app(&test_db.db, zelda_games(), Search::default()).await;
+
+    let (_, first) = send(&app, get("/games?query=zelda&sort=rating&limit=3")).await;
+    assert_eq!(
+        names(&first),
+        [
+            "Zelda: Ocarina of Time",
+            "Zelda: Breath of the Wild",
+            "Zelda: Minish Cap"
+        ]
+    );
+
+    // A game that would be listed first is added, and one already listed removed
+    games::Entity::create_or_update(
+        &test_db.db,
+        igdb_game(
+            8,
+            "Zelda: Link's Awakening",
+            json!({ "aggregated_rating": 99.0 }),
+        ),
+    )
+    .await
+    .unwrap();
+    games::Entity::delete_by_id(3)
+        .exec(&test_db.db)
+        .await
+        .unwrap();
+
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let (status, second) = send(
+        &app,
+        get(&format!(
+            "/games?query=zelda&sort=rating&limit=3&cursor={cursor}"
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        names(&second),
+        ["Zelda II", "Zelda", "Zelda: Skyward Sword"]
+    );
+}
+
+#[tokio::test]
+async fn rejects_cursors_of_other_searches() {
+    let test_db = test_db!();
+    let app = app(&test_db.db, zelda_games(), Search::default()).await;
+
+    let (_, page) = send(&app, get("/games?query=zelda&sort=rating&limit=2")).await;
+    let cursor = page["next_cursor"].as_str().unwrap();
+
+    for uri in [
+        format!("/games?query=zelda&sort=name&limit=2&cursor={cursor}"),
+        format!("/games?query=zeld&sort=rating&limit=2&cursor={cursor}"),
+        format!("/games?query=zelda&sort=rating&min_rating=1&limit=2&cursor={cursor}"),
+        "/games?query=zelda&sort=rating&cursor=MTA".to_string(),
+        format!("/games?query=zelda&sort=rating&cursor={cursor}x"),
+    ] {
+        let (status, body) = send(&app, get(&uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "requesting {uri}");
+        assert_eq!(body["code"], 3);
+    }
+}
+
+#[tokio::test]
+async fn clamps_page_sizes() {
+    let test_db = test_db!();
+    let search = Search {
+        max_page_size: 5,
+        ..Search::default()
+    };
+    let app = app(&test_db.db, zelda_games(), search).await;
+
+    for (limit, expected) in [(0, 1), (1, 1), (5, 5), (1000, 5)] {
+        let (status, page) = send(&app, get(&format!("/games?query=zelda&limit={limit}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&page).len(), expected, "limit {limit}");
+        assert!(page["next_cursor"].is_string(), "limit {limit}");
+    }
+
+    let (status, _) = send(&app, get("/games?query=zelda&limit=-1")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn refreshes_by_how_many_games_are_cached_whatever_the_page_size() {
+    let test_db = test_db!();
+    for id in 1..=12 {
+        games::Entity::create_or_update(
+            &test_db.db,
+            igdb_game(id, &format!("Doom {id}"), json!({})),
+        )
+        .await
+        .unwrap();
+    }
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    let (_, page) = send(&app, get("/games?query=doom&limit=2")).await;
+    assert_eq!(names(&page).len(), 2);
+    assert_eq!(igdb.request_count().await, 0);
+
+    let (_, page) = send(&app, get("/games?query=doom%201&limit=2")).await;
+    assert_eq!(names(&page), ["Doom 1", "Doom 10"]);
+    assert_eq!(igdb.request_count().await, 1);
+}
+
+#[tokio::test]
+async fn caches_games_found_on_igdb() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::new(vec![
+        igdb_game(1, "Portal", json!({})),
+        igdb_game(2, "Portal 2", json!({})),
+        igdb_game(3, "Half-Life", json!({})),
+    ]));
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    let (status, page) = send(&app, get("/games?query=portal")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&page), ["Portal", "Portal 2"]);
+    assert_eq!(
+        igdb.requests().await,
+        [FakeRequest::Search(GameSearch::new("portal"))]
+    );
+
+    // Searched recently, so served from the cache alone
+    let (_, page) = send(&app, get("/games?query=portal")).await;
+    assert_eq!(names(&page), ["Portal", "Portal 2"]);
+    assert_eq!(igdb.request_count().await, 1);
+
+    let (status, game) = send(&app, get("/games/2")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(game["name"], "Portal 2");
+
+    // Never found by a search, so never cached
+    let (status, body) = send(&app, get("/games/3")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], 2);
+}
+
+#[tokio::test]
+async fn serves_cached_games_while_igdb_is_offline() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::new(vec![igdb_game(1, "Portal", json!({}))]));
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    send(&app, get("/games?query=portal")).await;
+    igdb.set_status(IgdbStatus::Offline);
+
+    let (status, page) = send(&app, get("/games?query=port")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&page), ["Portal"]);
+
+    let (status, page) = send(&app, get("/games?query=half-life")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(names(&page).is_empty());
+
+    assert_eq!(igdb.request_count().await, 1);
+}
+
+#[tokio::test]
+async fn shares_one_igdb_search_between_concurrent_identical_searches() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::new(vec![igdb_game(1, "Portal", json!({}))]));
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    let pages = join_all((0..3).map(|_| send(&app, get("/games?query=portal")))).await;
+
+    for (status, page) in pages {
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&page), ["Portal"]);
+    }
+    assert_eq!(igdb.request_count().await, 1);
+}
+
+#[tokio::test]
+async fn searches_igdb_once_for_queries_naming_the_same_games() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::new(vec![igdb_game(1, "Halo: Reach", json!({}))]));
+    let app = igdbc::routes::router(AppState::new(test_db.db.clone(), igdb.clone()));
+
+    for query in ["Halo:%20Reach", "halo%20reach", "HALO%20REACH!"] {
+        let (status, page) = send(&app, get(&format!("/games?query={query}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&page), ["Halo: Reach"], "searching for {query}");
+    }
+    assert_eq!(igdb.request_count().await, 1);
+}
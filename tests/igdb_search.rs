@@ -0,0 +1,121 @@
+mod common;
+
+use common::{igdb_game, test_db};
+use futures::future::join_all;
+use igdbc::igdb::fake::FakeIgdb;
+use igdbc::models::_entities::{game_platforms, games, platforms};
+use igdbc::models::games::GameFilter;
+use sea_orm::{ConnectionTrait, EntityTrait, QueryOrder};
+use serde_json::json;
+
+#[tokio::test]
+async fn stores_games_with_their_references_and_aliases() {
+    let test_db = test_db!();
+    let igdb = FakeIgdb::new(vec![igdb_game(
+        1,
+        "Portal",
+        json!({
+            "alternative_names": [{ "id": 10, "name": "Portal: Still Alive" }],
+            "platforms": [{ "id": 6, "name": "PC (Microsoft Windows)" }],
+        }),
+    )]);
+
+    let found = igdbc::search_igdb(&test_db.db, &igdb, "portal".into(), &GameFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+
+    let dtos = games::Entity::load_dtos(&test_db.db, found).await.unwrap();
+    assert_eq!(
+        dtos[0].platforms,
+        Some(vec!["PC (Microsoft Windows)".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn resolves_placeholder_references_named_like_those_just_fetched() {
+    let test_db = test_db!();
+    let db = &test_db.db;
+
+    // A game stored before references had IGDB ids, linked to placeholders
+    igdbc::search_igdb(
+        db,
+        &FakeIgdb::new(vec![igdb_game(2, "Half-Life", json!({}))]),
+        "half-life".into(),
+        &GameFilter::default(),
+    )
+    .await
+    .unwrap();
+    db.execute_unprepared(
+        r#"
+        INSERT INTO platforms (id, name) VALUES (-1, 'PC (Microsoft Windows)'), (-2, 'Xbox');
+        INSERT INTO game_platforms (game_id, platform_id) VALUES (2, -1), (2, -2);
+        "#,
+    )
+    .await
+    .unwrap();
+
+    let igdb = FakeIgdb::new(vec![igdb_game(
+        1,
+        "Portal",
+        json!({ "platforms": [{ "id": 6, "name": "PC (Microsoft Windows)" }] }),
+    )]);
+    igdbc::search_igdb(db, &igdb, "portal".into(), &GameFilter::default())
+        .await
+        .unwrap();
+
+    let platforms = platforms::Entity::find()
+        .order_by_asc(platforms::Column::Id)
+        .all(db)
+        .await
+        .unwrap();
+    assert_eq!(
+        platforms.into_iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![-2, 6]
+    );
+
+    let links = game_platforms::Entity::find()
+        .order_by_asc(game_platforms::Column::GameId)
+        .order_by_asc(game_platforms::Column::PlatformId)
+        .all(db)
+        .await
+        .unwrap();
+    assert_eq!(
+        links
+            .into_iter()
+            .map(|link| (link.game_id, link.platform_id))
+            .collect::<Vec<_>>(),
+        vec![(1, 6), (2, -2), (2, 6)]
+    );
+}
+
+#[tokio::test]
+async fn upserts_the_same_game_concurrently() {
+    let test_db = test_db!();
+    let game = igdb_game(
+        1,
+        "Portal",
+        json!({
+            "alternative_names": [{ "id": 10, "name": "Portal: Still Alive" }],
+            "platforms": [{ "id": 6, "name": "PC (Microsoft Windows)" }],
+        }),
+    );
+
+    let saved =
+        join_all((0..20).map(|_| games::Entity::create_or_update(&test_db.db, game.clone()))).await;
+
+    for result in saved {
+        assert_eq!(result.unwrap().name, "Portal");
+    }
+    let dtos = games::Entity::load_dtos(
+        &test_db.db,
+        games::Entity::find().all(&test_db.db).await.unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(dtos.len(), 1);
+    assert_eq!(
+        dtos[0].platforms,
+        Some(vec!["PC (Microsoft Windows)".to_string()])
+    );
+}
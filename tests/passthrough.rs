@@ -0,0 +1,135 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use chrono::{Duration, Utc};
+use common::{send, test_db};
+use igdbc::configuration;
+use igdbc::igdb::fake::{FakeIgdb, FakeRequest};
+use igdbc::igdb::IgdbStatus;
+use igdbc::models::_entities::igdb_responses;
+use igdbc::AppState;
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+use serde_json::json;
+
+const API_KEY: &str = "secret";
+
+fn app(db: &sea_orm::DatabaseConnection, igdb: Arc<FakeIgdb>, cache_ttl_secs: u64) -> Router {
+    let passthrough = configuration::Passthrough {
+        api_keys: vec![API_KEY.to_string()],
+        cache_ttl_secs,
+        endpoints: vec!["games".to_string()],
+        fields: [(
+            "games".to_string(),
+            vec!["id".to_string(), "name".to_string()],
+        )]
+        .into(),
+    };
+
+    igdbc::routes::router(AppState::new(db.clone(), igdb).with_passthrough((&passthrough).into()))
+}
+
+fn query(endpoint: &str, api_key: Option<&str>, body: &str) -> Request<Body> {
+    let mut request = Request::post(format!("/igdb/{endpoint}"));
+    if let Some(api_key) = api_key {
+        request = request.header("Authorization", format!("Bearer {api_key}"));
+    }
+    request.body(Body::from(body.to_string())).unwrap()
+}
+
+#[tokio::test]
+async fn requires_an_api_key() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = app(&test_db.db, igdb.clone(), 60);
+
+    for api_key in [None, Some("wrong"), Some("secre"), Some("secrets")] {
+        let (status, _) = send(&app, query("games", api_key, "fields name;")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let (status, _) = send(&app, query("games", Some(API_KEY), "fields name;")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(igdb.request_count().await, 1);
+}
+
+#[tokio::test]
+async fn rejects_invalid_and_forbidden_queries_before_going_upstream() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = app(&test_db.db, igdb.clone(), 60);
+
+    let (status, _) = send(&app, query("games", Some(API_KEY), "fields name")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&app, query("companies", Some(API_KEY), "fields name;")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, query("games", Some(API_KEY), "fields summary;")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    assert_eq!(igdb.request_count().await, 0);
+}
+
+#[tokio::test]
+async fn serves_equivalent_queries_from_the_cache() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = app(&test_db.db, igdb.clone(), 60);
+
+    let (status, body) = send(
+        &app,
+        query("games", Some(API_KEY), "fields name,id; where id = 1;"),
+    )
+    .await;
+    assert_eq!((status, body), (StatusCode::OK, json!([])));
+
+    let (status, _) = send(
+        &app,
+        query("games", Some(API_KEY), "where id=1;\nfields id, name;"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(
+        igdb.requests().await,
+        vec![FakeRequest::RawQuery {
+            endpoint: "games".to_string(),
+            query: "fields id, name;\nwhere id = 1;\n".to_string(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn refetches_stale_responses_unless_igdb_is_offline() {
+    let test_db = test_db!();
+    let igdb = Arc::new(FakeIgdb::default());
+    let app = app(&test_db.db, igdb.clone(), 60);
+
+    send(&app, query("games", Some(API_KEY), "fields name;")).await;
+
+    // Age the cached response past its TTL and make it recognisable
+    let cached = igdb_responses::Entity::find()
+        .one(&test_db.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut cached = cached.into_active_model();
+    cached.fetched_at = Set((Utc::now() - Duration::minutes(2)).naive_utc());
+    cached.response = Set(json!([{ "id": 1, "name": "Cached" }]));
+    cached.update(&test_db.db).await.unwrap();
+
+    igdb.set_status(IgdbStatus::Offline);
+    let (status, body) = send(&app, query("games", Some(API_KEY), "fields name;")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!([{ "id": 1, "name": "Cached" }]));
+    assert_eq!(igdb.request_count().await, 1);
+
+    igdb.set_status(IgdbStatus::Online);
+    let (status, body) = send(&app, query("games", Some(API_KEY), "fields name;")).await;
+    assert_eq!((status, body), (StatusCode::OK, json!([])));
+    assert_eq!(igdb.request_count().await, 2);
+}
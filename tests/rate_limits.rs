@@ -0,0 +1,67 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::test_db;
+use futures::future::join_all;
+use igdbc::models::_entities::rate_limits;
+use sea_orm::DatabaseConnection;
+
+const LIMIT: &str = "igdb";
+const INTERVAL: Duration = Duration::from_millis(200);
+
+async fn reserve(db: &DatabaseConnection, burst: u32) -> Duration {
+    rate_limits::Entity::reserve(db, LIMIT, INTERVAL, burst)
+        .await
+        .unwrap()
+}
+
+/// Each wait is shortened by however long after the first reservation, made no earlier than
+/// `started`, it was made
+fn assert_near(actual: Duration, expected: Duration, started: Instant) {
+    let tolerance = started.elapsed();
+    assert!(
+        actual <= expected && expected - actual < tolerance,
+        "expected about {expected:?}, got {actual:?}"
+    );
+}
+
+#[tokio::test]
+async fn allows_a_burst_of_requests_up_to_the_limit() {
+    let test_db = test_db!();
+    let started = Instant::now();
+
+    for _ in 0..3 {
+        assert_eq!(reserve(&test_db.db, 3).await, Duration::ZERO);
+    }
+    assert_near(reserve(&test_db.db, 3).await, INTERVAL, started);
+}
+
+#[tokio::test]
+async fn spaces_requests_after_a_burst_at_the_rate() {
+    let test_db = test_db!();
+    let started = Instant::now();
+
+    for _ in 0..2 {
+        reserve(&test_db.db, 2).await;
+    }
+    for intervals in 1..=4 {
+        assert_near(reserve(&test_db.db, 2).await, INTERVAL * intervals, started);
+    }
+}
+
+#[tokio::test]
+async fn shares_one_limit_between_connections() {
+    let test_db = test_db!();
+    let other = test_db.connect().await;
+    let started = Instant::now();
+
+    let mut waits =
+        join_all((0..10).map(|n| reserve(if n % 2 == 0 { &test_db.db } else { &other }, 2))).await;
+    waits.sort();
+
+    assert_eq!(waits[..2], [Duration::ZERO; 2]);
+    for (intervals, wait) in (1..).zip(&waits[2..]) {
+        assert_near(*wait, INTERVAL * intervals, started);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Another name a game can be searched for by, such as an abbreviation
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct GameAliasDTO {
+    pub id: i32,
+
+    /// The ID of the game this alias refers to, as per IGDB
+    pub game_id: i32,
+
+    pub alias: String,
+
+    /// Where this alias came from
+    pub source: AliasSource,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasSource {
+    /// One of the game's alternative names on IGDB. These are replaced whenever the game is
+    /// refreshed from IGDB
+    Igdb,
+
+    /// Added by an administrator
+    Curated,
+}
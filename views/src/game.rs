@@ -46,3 +46,12 @@ pub struct GameDTO {
     /// A list of platforms that this game is available on
     pub platforms: Option<Vec<String>>,
 }
+
+/// One page of the games found by a search
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct GamePage {
+    pub games: Vec<GameDTO>,
+
+    /// Pass this as the `cursor` of the same search to get the next page. Absent on the last page
+    pub next_cursor: Option<String>,
+}
@@ -1,2 +1,4 @@
+mod alias;
 mod game;
-pub use game::GameDTO;
+pub use alias::{AliasSource, GameAliasDTO};
+pub use game::{GameDTO, GamePage};